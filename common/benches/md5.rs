@@ -0,0 +1,102 @@
+use std::hint::black_box;
+
+use common::md5::{Digest, Salted};
+use criterion::{criterion_group, criterion_main, Criterion};
+
+// The way days 5, 14 and 17 used to hash: format the input, then the digest
+// to a hex string, then scan its characters.
+
+fn hex_leading_zeros(salt: &str, index: u64) -> bool {
+    let hex = format!("{:x}", md5::compute(format!("{salt}{index}")));
+    hex.starts_with("00000")
+}
+
+fn hex_first_triple(salt: &str, index: u64) -> Option<char> {
+    let hex = format!("{:x}", md5::compute(format!("{salt}{index}")));
+    hex.chars()
+        .collect::<Vec<_>>()
+        .windows(3)
+        .find(|c| c[0] == c[1] && c[1] == c[2])
+        .map(|c| c[0])
+}
+
+fn hex_stretched(salt: &str, index: u64, rounds: usize) -> String {
+    (0..=rounds).fold(format!("{salt}{index}"), |hex, _| {
+        format!("{:x}", md5::compute(hex))
+    })
+}
+
+fn leading_zeros(c: &mut Criterion) {
+    let mut group = c.benchmark_group("leading zeros");
+    group.bench_function("hex string", |b| {
+        b.iter(|| {
+            (0..1000)
+                .filter(|i| hex_leading_zeros("abc", black_box(*i)))
+                .count()
+        })
+    });
+    let salted = Salted::new("abc");
+    group.bench_function("digest", |b| {
+        b.iter(|| {
+            (0..1000)
+                .filter(|i| salted.digest(black_box(*i)).leading_zeros() >= 5)
+                .count()
+        })
+    });
+    group.finish();
+}
+
+fn first_triple(c: &mut Criterion) {
+    let mut group = c.benchmark_group("first triple");
+    group.bench_function("hex string", |b| {
+        b.iter(|| {
+            (0..1000)
+                .filter_map(|i| hex_first_triple("abc", black_box(i)))
+                .count()
+        })
+    });
+    let salted = Salted::new("abc");
+    group.bench_function("digest", |b| {
+        b.iter(|| {
+            (0..1000)
+                .filter_map(|i| salted.digest(black_box(i)).first_run(3))
+                .count()
+        })
+    });
+    group.finish();
+}
+
+fn stretching(c: &mut Criterion) {
+    let mut group = c.benchmark_group("stretching");
+    group.bench_function("hex string", |b| {
+        b.iter(|| hex_stretched("abc", black_box(0), 2016))
+    });
+    let salted = Salted::new("abc");
+    group.bench_function("digest", |b| {
+        b.iter(|| salted.digest(black_box(0)).stretched(2016))
+    });
+    group.finish();
+}
+
+fn doors(c: &mut Criterion) {
+    let mut group = c.benchmark_group("doors");
+    group.bench_function("hex string", |b| {
+        b.iter(|| {
+            format!("{:x}", md5::compute(black_box("hijklDUR")))
+                .chars()
+                .take(4)
+                .filter(|c| matches!(c, 'b'..='f'))
+                .count()
+        })
+    });
+    group.bench_function("digest", |b| {
+        b.iter(|| {
+            let digest = Digest::of(black_box("hijklDUR"));
+            (0..4).filter(|n| digest.nibble(*n) >= 0xb).count()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, leading_zeros, first_triple, stretching, doors);
+criterion_main!(benches);
@@ -0,0 +1,794 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{Display, Write},
+};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ParseError {
+    MissingInstruction,
+    UnknownInstruction(String),
+    MissingOperand(String),
+    UnexpectedOperand(String),
+    InvalidRegister(String),
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::MissingInstruction => write!(f, "missing instruction"),
+            ParseError::UnknownInstruction(s) => write!(f, "unknown instruction `{s}`"),
+            ParseError::MissingOperand(s) => write!(f, "missing operand for `{s}`"),
+            ParseError::UnexpectedOperand(s) => write!(f, "unexpected operand `{s}`"),
+            ParseError::InvalidRegister(s) => write!(f, "invalid register `{s}`"),
+        }
+    }
+}
+
+impl Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegisterOrValue {
+    Register(char),
+    Value(i32),
+}
+
+impl TryFrom<&str> for RegisterOrValue {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        if let Ok(n) = value.parse::<i32>() {
+            Ok(Self::Value(n))
+        } else {
+            Ok(Self::Register(str_to_register(value)?))
+        }
+    }
+}
+
+pub fn str_to_register(s: &str) -> Result<char, ParseError> {
+    match s {
+        "a" | "b" | "c" | "d" => Ok(s.chars().next().unwrap()),
+        _ => Err(ParseError::InvalidRegister(s.to_string())),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Instruction {
+    Cpy(RegisterOrValue, RegisterOrValue),
+    Inc(RegisterOrValue),
+    Dec(RegisterOrValue),
+    Jnz(RegisterOrValue, RegisterOrValue),
+    Tgl(RegisterOrValue),
+    Out(RegisterOrValue),
+}
+
+/// What `tgl` turns an instruction into, by number of operands: the first
+/// named instruction becomes the second, any other becomes the third.
+const TOGGLE_RULES: [(usize, &str, &str, &str); 2] =
+    [(1, "inc", "dec", "inc"), (2, "jnz", "cpy", "jnz")];
+
+impl Instruction {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Instruction::Cpy(..) => "cpy",
+            Instruction::Inc(_) => "inc",
+            Instruction::Dec(_) => "dec",
+            Instruction::Jnz(..) => "jnz",
+            Instruction::Tgl(_) => "tgl",
+            Instruction::Out(_) => "out",
+        }
+    }
+
+    pub fn operands(&self) -> Vec<RegisterOrValue> {
+        match self {
+            Instruction::Cpy(x, y) | Instruction::Jnz(x, y) => vec![x.clone(), y.clone()],
+            Instruction::Inc(x)
+            | Instruction::Dec(x)
+            | Instruction::Tgl(x)
+            | Instruction::Out(x) => {
+                vec![x.clone()]
+            }
+        }
+    }
+
+    fn from_parts(name: &str, operands: Vec<RegisterOrValue>) -> Option<Self> {
+        let mut operands = operands.into_iter();
+        let instruction = match name {
+            "cpy" => Self::Cpy(operands.next()?, operands.next()?),
+            "inc" => Self::Inc(operands.next()?),
+            "dec" => Self::Dec(operands.next()?),
+            "jnz" => Self::Jnz(operands.next()?, operands.next()?),
+            "tgl" => Self::Tgl(operands.next()?),
+            "out" => Self::Out(operands.next()?),
+            _ => return None,
+        };
+        operands.next().is_none().then_some(instruction)
+    }
+
+    /// Applies the `tgl` rules. The result may be invalid, like `cpy 1 2`,
+    /// in which case executing it does nothing.
+    pub fn toggled(&self) -> Self {
+        let operands = self.operands();
+        match TOGGLE_RULES
+            .iter()
+            .find(|(arity, ..)| *arity == operands.len())
+        {
+            Some((_, from, to, otherwise)) => {
+                let name = if self.name() == *from { to } else { otherwise };
+                Self::from_parts(name, operands).unwrap()
+            }
+            None => self.clone(),
+        }
+    }
+}
+
+impl TryFrom<&str> for Instruction {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let mut split = value.split_whitespace();
+        let name = split.next().ok_or(ParseError::MissingInstruction)?;
+        let mut operand = || {
+            split
+                .next()
+                .ok_or_else(|| ParseError::MissingOperand(name.to_string()))
+        };
+        let instruction = match name {
+            "cpy" => Self::Cpy(operand()?.try_into()?, operand()?.try_into()?),
+            "inc" => Self::Inc(RegisterOrValue::Register(str_to_register(operand()?)?)),
+            "dec" => Self::Dec(RegisterOrValue::Register(str_to_register(operand()?)?)),
+            "jnz" => Self::Jnz(operand()?.try_into()?, operand()?.try_into()?),
+            "tgl" => Self::Tgl(operand()?.try_into()?),
+            "out" => Self::Out(operand()?.try_into()?),
+            s => return Err(ParseError::UnknownInstruction(s.to_string())),
+        };
+        match split.next() {
+            Some(s) => Err(ParseError::UnexpectedOperand(s.to_string())),
+            None => Ok(instruction),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssembleError {
+    UndefinedLabel { label: String, line: usize },
+    DuplicateLabel { label: String, line: usize },
+    InvalidLabel { label: String, line: usize },
+    InvalidInstruction { error: ParseError, line: usize },
+}
+
+impl Display for AssembleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssembleError::UndefinedLabel { label, line } => {
+                write!(f, "line {line}: undefined label `{label}`")
+            }
+            AssembleError::DuplicateLabel { label, line } => {
+                write!(f, "line {line}: duplicate label `{label}`")
+            }
+            AssembleError::InvalidLabel { label, line } => {
+                write!(f, "line {line}: invalid label `{label}`")
+            }
+            AssembleError::InvalidInstruction { error, line } => {
+                write!(f, "line {line}: {error}")
+            }
+        }
+    }
+}
+
+impl Error for AssembleError {}
+
+/// Labels must not be mistaken for an operand, so single characters
+/// (registers) and numbers are rejected.
+fn is_label(s: &str) -> bool {
+    s.len() > 1
+        && s.parse::<i32>().is_err()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Assembles the extended assembunny syntax into plain instructions.
+///
+/// On top of the puzzle syntax, a line may start with a `label:` definition,
+/// everything after a `;` is a comment, blank lines are ignored and the
+/// target of a `jnz` may be a label, which is resolved to a relative offset.
+pub fn assemble(source: &str) -> Result<Vec<Instruction>, AssembleError> {
+    let mut labels = HashMap::new();
+    let mut statements = vec![];
+    for (line, text) in source.lines().enumerate() {
+        let line = line + 1;
+        let mut text = text.split(';').next().unwrap().trim();
+        if let Some((label, rest)) = text.split_once(':') {
+            let label = label.trim();
+            if !is_label(label) {
+                return Err(AssembleError::InvalidLabel {
+                    label: label.to_string(),
+                    line,
+                });
+            }
+            if labels.insert(label, statements.len()).is_some() {
+                return Err(AssembleError::DuplicateLabel {
+                    label: label.to_string(),
+                    line,
+                });
+            }
+            text = rest.trim();
+        }
+        if !text.is_empty() {
+            statements.push((line, text));
+        }
+    }
+
+    statements
+        .iter()
+        .enumerate()
+        .map(|(address, (line, text))| {
+            let mut split = text.split_whitespace();
+            match (split.next(), split.next(), split.next(), split.next()) {
+                (Some("jnz"), Some(v), Some(target), None) if is_label(target) => {
+                    let destination =
+                        labels
+                            .get(target)
+                            .ok_or_else(|| AssembleError::UndefinedLabel {
+                                label: target.to_string(),
+                                line: *line,
+                            })?;
+                    let offset = *destination as i32 - address as i32;
+                    let v = v
+                        .try_into()
+                        .map_err(|error| AssembleError::InvalidInstruction {
+                            error,
+                            line: *line,
+                        })?;
+                    Ok(Instruction::Jnz(v, RegisterOrValue::Value(offset)))
+                }
+                _ => Instruction::try_from(*text)
+                    .map_err(|error| AssembleError::InvalidInstruction { error, line: *line }),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Halted,
+    Running,
+}
+
+/// Output sink for programs whose `out` instructions are of no interest.
+struct Discard;
+
+impl Write for Discard {
+    fn write_str(&mut self, _: &str) -> std::fmt::Result {
+        Ok(())
+    }
+}
+
+/// The assembunny machine shared by days 12, 23 and 25, which used to have
+/// an interpreter each.
+///
+/// Where those had drifted apart, it follows the puzzle text: `jnz` jumps
+/// by a register or a number, instructions made invalid by `tgl` (such as a
+/// `cpy` to a number) are skipped, `tgl` turns `out` into `inc`, and the
+/// multiplication shortcut only fires on loops whose jumps really are `-2`
+/// and `-5`.
+#[derive(Debug, Clone)]
+pub struct Computer {
+    registers: HashMap<char, i32>,
+    instructions: Vec<Instruction>,
+    cursor: i32,
+    optimize: bool,
+}
+
+impl From<Vec<Instruction>> for Computer {
+    fn from(instructions: Vec<Instruction>) -> Self {
+        let mut registers = HashMap::new();
+        registers.insert('a', 0);
+        registers.insert('b', 0);
+        registers.insert('c', 0);
+        registers.insert('d', 0);
+        Self {
+            registers,
+            instructions,
+            cursor: 0,
+            optimize: true,
+        }
+    }
+}
+
+impl TryFrom<&str> for Computer {
+    type Error = AssembleError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Ok(Self::from(assemble(value)?))
+    }
+}
+
+fn process_multiply(instructions: &[Instruction]) -> Option<(char, char, char, char)> {
+    let cpy =
+        if let Instruction::Cpy(RegisterOrValue::Register(r1), RegisterOrValue::Register(r2)) =
+            instructions[0]
+        {
+            (r1, r2)
+        } else {
+            return None;
+        };
+    let inc = if let Instruction::Inc(RegisterOrValue::Register(r)) = instructions[1] {
+        r
+    } else {
+        return None;
+    };
+    let dec1 = if let Instruction::Dec(RegisterOrValue::Register(r)) = instructions[2] {
+        r
+    } else {
+        return None;
+    };
+    let jnz1 = if let Instruction::Jnz(RegisterOrValue::Register(r), RegisterOrValue::Value(-2)) =
+        instructions[3]
+    {
+        r
+    } else {
+        return None;
+    };
+    let dec2 = if let Instruction::Dec(RegisterOrValue::Register(r)) = instructions[4] {
+        r
+    } else {
+        return None;
+    };
+    let jnz2 = if let Instruction::Jnz(RegisterOrValue::Register(r), RegisterOrValue::Value(-5)) =
+        instructions[5]
+    {
+        r
+    } else {
+        return None;
+    };
+    if !(cpy.1 == dec1 && cpy.1 == jnz1 && dec2 == jnz2) {
+        return None;
+    };
+    // The loop only multiplies if no register plays two roles
+    let registers = [cpy.0, dec2, cpy.1, inc];
+    if (1..4).any(|i| registers[..i].contains(&registers[i])) {
+        return None;
+    }
+    Some((cpy.0, dec2, cpy.1, inc))
+}
+
+impl Computer {
+    pub fn set_register(&mut self, r: char, v: i32) {
+        self.registers.entry(r).and_modify(|value| *value = v);
+    }
+
+    /// Turns the multiplication shortcut on or off, mostly to check that it
+    /// agrees with plain execution.
+    pub fn set_optimize(&mut self, optimize: bool) {
+        self.optimize = optimize;
+    }
+
+    pub fn get_register(&self, r: char) -> i32 {
+        *self.registers.get(&r).unwrap()
+    }
+
+    fn value_of(&self, v: &RegisterOrValue) -> i32 {
+        match v {
+            RegisterOrValue::Register(reg) => self.get_register(*reg),
+            RegisterOrValue::Value(val) => *val,
+        }
+    }
+
+    /// Runs the program until the cursor leaves it, discarding any output.
+    pub fn execute(&mut self) {
+        self.run(&mut Discard, usize::MAX).unwrap();
+    }
+
+    /// Runs at most `max_steps` instructions, writing every `out` to `output`.
+    ///
+    /// Returns `Status::Running` if the program was still going when the
+    /// limit was hit. Arithmetic wraps around, and jumping before the first
+    /// or past the last instruction halts the program.
+    pub fn run<W: Write>(
+        &mut self,
+        output: &mut W,
+        max_steps: usize,
+    ) -> Result<Status, std::fmt::Error> {
+        self.run_with(|value| write!(output, "{value}"), max_steps)
+    }
+
+    /// Like `run`, but hands every `out` value to `output` as it is, for
+    /// callers that need the values rather than their text.
+    pub fn run_with<F: FnMut(i32) -> std::fmt::Result>(
+        &mut self,
+        mut output: F,
+        max_steps: usize,
+    ) -> Result<Status, std::fmt::Error> {
+        let mut count = 0;
+        while let Some(instruction) = usize::try_from(self.cursor)
+            .ok()
+            .and_then(|cursor| self.instructions.get(cursor))
+        {
+            if count == max_steps {
+                return Ok(Status::Running);
+            }
+            let cursor = self.cursor as usize;
+            let process_multiply = if self.optimize && cursor + 6 <= self.instructions.len() {
+                process_multiply(&self.instructions[cursor..cursor + 6])
+            } else {
+                None
+            };
+            if let Some((r1, r2, r3, rd)) = process_multiply {
+                let mult1 = self.get_register(r1);
+                let mult2 = self.get_register(r2);
+                self.registers
+                    .entry(rd)
+                    .and_modify(|value| *value = value.wrapping_add(mult1.wrapping_mul(mult2)));
+                self.registers.entry(r2).and_modify(|value| *value = 0);
+                self.registers.entry(r3).and_modify(|value| *value = 0);
+                self.cursor += 6;
+            } else {
+                match instruction {
+                    Instruction::Cpy(v, RegisterOrValue::Register(r)) => {
+                        let value = self.value_of(v);
+                        self.set_register(*r, value);
+                        self.cursor += 1;
+                    }
+                    Instruction::Inc(RegisterOrValue::Register(r)) => {
+                        self.registers
+                            .entry(*r)
+                            .and_modify(|value| *value = value.wrapping_add(1));
+                        self.cursor += 1;
+                    }
+                    Instruction::Dec(RegisterOrValue::Register(r)) => {
+                        self.registers
+                            .entry(*r)
+                            .and_modify(|value| *value = value.wrapping_sub(1));
+                        self.cursor += 1;
+                    }
+                    Instruction::Jnz(v, c) => {
+                        if self.value_of(v) != 0 {
+                            self.cursor = self.cursor.wrapping_add(self.value_of(c));
+                        } else {
+                            self.cursor += 1;
+                        }
+                    }
+                    Instruction::Tgl(v) => {
+                        let pos = self.value_of(v).wrapping_add(self.cursor);
+                        if let Some(instruction) = usize::try_from(pos)
+                            .ok()
+                            .and_then(|pos| self.instructions.get_mut(pos))
+                        {
+                            *instruction = instruction.toggled();
+                        }
+                        self.cursor += 1;
+                    }
+                    Instruction::Out(v) => {
+                        output(self.value_of(v))?;
+                        self.cursor += 1;
+                    }
+                    // Invalid forms produced by `tgl`, such as `cpy 1 2`
+                    _ => self.cursor += 1,
+                }
+            };
+            count += 1;
+        }
+        Ok(Status::Halted)
+    }
+}
+
+#[cfg(test)]
+mod assembunny_tests {
+    use parameterized::parameterized;
+    use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+    use super::*;
+
+    fn parse(source: &str) -> Vec<Instruction> {
+        source
+            .lines()
+            .map(|l| Instruction::try_from(l).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_assemble_labels() {
+        let source = r#"
+; a = 2 * 3
+    cpy 2 b
+outer:
+    cpy 3 c
+inner: inc a    ; one unit at a time
+    dec c
+    jnz c inner
+    dec b
+    jnz b outer
+    jnz 1 end
+    inc d
+end:"#;
+        let expected = parse(
+            r#"cpy 2 b
+cpy 3 c
+inc a
+dec c
+jnz c -2
+dec b
+jnz b -5
+jnz 1 2
+inc d"#,
+        );
+        assert_eq!(assemble(source), Ok(expected));
+    }
+
+    #[test]
+    fn test_assemble_plain_program() {
+        let source = "cpy 41 a\ninc a\njnz a 2\ndec a";
+        assert_eq!(assemble(source), Ok(parse(source)));
+    }
+
+    #[test]
+    fn test_assemble_errors() {
+        assert_eq!(
+            assemble("inc a\njnz a nowhere"),
+            Err(AssembleError::UndefinedLabel {
+                label: "nowhere".to_string(),
+                line: 2
+            })
+        );
+        assert_eq!(
+            assemble("loop: inc a\n\nloop: dec a"),
+            Err(AssembleError::DuplicateLabel {
+                label: "loop".to_string(),
+                line: 3
+            })
+        );
+        assert_eq!(
+            assemble("a: inc a"),
+            Err(AssembleError::InvalidLabel {
+                label: "a".to_string(),
+                line: 1
+            })
+        );
+        assert_eq!(
+            assemble("inc a\nmul a b"),
+            Err(AssembleError::InvalidInstruction {
+                error: ParseError::UnknownInstruction("mul".to_string()),
+                line: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            Instruction::try_from("hlt"),
+            Err(ParseError::UnknownInstruction("hlt".to_string()))
+        );
+        assert_eq!(
+            Instruction::try_from("inc ab"),
+            Err(ParseError::InvalidRegister("ab".to_string()))
+        );
+        assert_eq!(
+            Instruction::try_from("cpy 1"),
+            Err(ParseError::MissingOperand("cpy".to_string()))
+        );
+        assert_eq!(
+            Instruction::try_from("dec a b"),
+            Err(ParseError::UnexpectedOperand("b".to_string()))
+        );
+        assert_eq!(
+            Instruction::try_from(""),
+            Err(ParseError::MissingInstruction)
+        );
+    }
+
+    #[test]
+    fn test_execute_halts_out_of_bounds() {
+        let mut computer = Computer::try_from("inc a\njnz a -5\ninc a").unwrap();
+        computer.execute();
+        assert_eq!(computer.get_register('a'), 1);
+
+        let mut computer = Computer::try_from("cpy 2147483647 a\ninc a\njnz 1 a").unwrap();
+        computer.execute();
+        assert_eq!(computer.get_register('a'), i32::MIN);
+    }
+
+    #[test]
+    fn test_run_step_limit() {
+        let mut computer = Computer::try_from("out a\ninc a\njnz 1 -2").unwrap();
+        let mut output = String::new();
+        assert_eq!(computer.run(&mut output, 9), Ok(Status::Running));
+        assert_eq!(output, "012");
+    }
+
+    #[test]
+    fn test_run_with() {
+        let mut computer = Computer::try_from("cpy 9 a\nout a\ninc a\njnz 1 -2").unwrap();
+        let mut values = vec![];
+        let status = computer.run_with(
+            |value| {
+                values.push(value);
+                Ok(())
+            },
+            8,
+        );
+        assert_eq!(status, Ok(Status::Running));
+        assert_eq!(values, vec![9, 10, 11]);
+    }
+
+    #[parameterized(
+        input = { "inc a", "dec a", "tgl a", "out a", "cpy a b", "cpy 1 a", "jnz a b", "jnz 1 a" },
+        expected = { "dec a", "inc a", "inc a", "inc a", "jnz a b", "jnz 1 a", "cpy a b", "cpy 1 a" }
+    )]
+    fn test_toggled(input: &str, expected: &str) {
+        let instruction = Instruction::try_from(input).unwrap();
+        assert_eq!(
+            instruction.toggled(),
+            Instruction::try_from(expected).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_toggled_invalid() {
+        assert_eq!(
+            Instruction::try_from("out 5").unwrap().toggled(),
+            Instruction::Inc(RegisterOrValue::Value(5))
+        );
+        assert_eq!(
+            Instruction::try_from("tgl 2").unwrap().toggled(),
+            Instruction::Inc(RegisterOrValue::Value(2))
+        );
+        assert_eq!(
+            Instruction::try_from("jnz 1 2").unwrap().toggled(),
+            Instruction::Cpy(RegisterOrValue::Value(1), RegisterOrValue::Value(2))
+        );
+    }
+
+    #[test]
+    fn test_execute_skips_invalid_toggled() {
+        let input = r#"tgl 3
+tgl 3
+tgl 3
+out 7
+jnz 1 2
+out a"#;
+        let mut computer = Computer::try_from(input).unwrap();
+        let mut output = String::new();
+        assert_eq!(computer.run(&mut output, 100), Ok(Status::Halted));
+        assert_eq!(output, "");
+        assert_eq!(computer.get_register('a'), 1);
+    }
+
+    const REGISTERS: [char; 4] = ['a', 'b', 'c', 'd'];
+
+    /// Straightforward interpreter for the `cpy`/`inc`/`dec`/`jnz` subset,
+    /// following the puzzle text literally. It is the oracle the optimized
+    /// `Computer` is checked against.
+    ///
+    /// The interpreters days 12, 23 and 25 used to have are gone, all three
+    /// now run `Computer`, so this one is written apart from it instead:
+    /// no shared code, no shortcut, and its own bounds and register checks.
+    /// Porting the old interpreters back would only compare against the
+    /// drift they had, such as day 23 multiplying loops of any shape.
+    fn reference_execute(
+        program: &[Instruction],
+        mut registers: [i32; 4],
+        max_steps: usize,
+    ) -> Option<[i32; 4]> {
+        let index = |r: char| REGISTERS.iter().position(|&c| c == r).unwrap();
+        let value = |registers: &[i32; 4], v: &RegisterOrValue| match v {
+            RegisterOrValue::Register(r) => registers[index(*r)],
+            RegisterOrValue::Value(n) => *n,
+        };
+        let mut cursor = 0i64;
+        for _ in 0..max_steps {
+            if cursor < 0 || cursor >= program.len() as i64 {
+                return Some(registers);
+            }
+            match &program[cursor as usize] {
+                Instruction::Cpy(v, RegisterOrValue::Register(r)) => {
+                    registers[index(*r)] = value(&registers, v)
+                }
+                Instruction::Cpy(_, RegisterOrValue::Value(_)) => {}
+                Instruction::Inc(RegisterOrValue::Register(r)) => {
+                    registers[index(*r)] = registers[index(*r)].wrapping_add(1)
+                }
+                Instruction::Dec(RegisterOrValue::Register(r)) => {
+                    registers[index(*r)] = registers[index(*r)].wrapping_sub(1)
+                }
+                Instruction::Jnz(v, c) => {
+                    if value(&registers, v) != 0 {
+                        cursor += value(&registers, c) as i64;
+                        continue;
+                    }
+                }
+                i => unreachable!("{i:?} is not in the common subset"),
+            }
+            cursor += 1;
+        }
+        None
+    }
+
+    fn random_operand(rng: &mut StdRng) -> RegisterOrValue {
+        if rng.gen_bool(0.5) {
+            RegisterOrValue::Register(*REGISTERS.choose(rng).unwrap())
+        } else {
+            RegisterOrValue::Value(rng.gen_range(-3..=5))
+        }
+    }
+
+    fn random_program(rng: &mut StdRng) -> Vec<Instruction> {
+        let mut program = vec![];
+        let len = rng.gen_range(1..=12);
+        while program.len() < len {
+            let register = *REGISTERS.choose(rng).unwrap();
+            match rng.gen_range(0..10) {
+                0..=1 => program.push(Instruction::Cpy(random_operand(rng), random_operand(rng))),
+                2..=3 => program.push(Instruction::Inc(RegisterOrValue::Register(register))),
+                4..=5 => program.push(Instruction::Dec(RegisterOrValue::Register(register))),
+                6..=8 => program.push(Instruction::Jnz(
+                    random_operand(rng),
+                    RegisterOrValue::Value(rng.gen_range(-4..=4)),
+                )),
+                _ => {
+                    // Nested counting loop, the shape the multiplication
+                    // shortcut looks for, with registers and offsets that do
+                    // not always fit it
+                    let mut r = || *REGISTERS.choose(rng).unwrap();
+                    let (source, result, inner, outer) = (r(), r(), r(), r());
+                    let mut offset = |expected| {
+                        if rng.gen_bool(0.8) {
+                            expected
+                        } else {
+                            rng.gen_range(-6..=2)
+                        }
+                    };
+                    let (inner_offset, outer_offset) = (offset(-2), offset(-5));
+                    program.extend([
+                        Instruction::Cpy(
+                            RegisterOrValue::Register(source),
+                            RegisterOrValue::Register(inner),
+                        ),
+                        Instruction::Inc(RegisterOrValue::Register(result)),
+                        Instruction::Dec(RegisterOrValue::Register(inner)),
+                        Instruction::Jnz(
+                            RegisterOrValue::Register(inner),
+                            RegisterOrValue::Value(inner_offset),
+                        ),
+                        Instruction::Dec(RegisterOrValue::Register(outer)),
+                        Instruction::Jnz(
+                            RegisterOrValue::Register(outer),
+                            RegisterOrValue::Value(outer_offset),
+                        ),
+                    ]);
+                }
+            }
+        }
+        program
+    }
+
+    #[test]
+    fn test_differential_execution() {
+        let mut rng = StdRng::seed_from_u64(2016);
+        let mut checked = 0;
+        for _ in 0..5000 {
+            let program = random_program(&mut rng);
+            let registers: [i32; 4] = std::array::from_fn(|_| rng.gen_range(0..=6));
+            let Some(expected) = reference_execute(&program, registers, 10_000) else {
+                continue;
+            };
+            for optimize in [false, true] {
+                let mut computer = Computer::from(program.clone());
+                computer.set_optimize(optimize);
+                REGISTERS
+                    .iter()
+                    .zip(registers)
+                    .for_each(|(r, v)| computer.set_register(*r, v));
+                let status = computer.run(&mut String::new(), 100_000).unwrap();
+                let actual = REGISTERS.map(|r| computer.get_register(r));
+                assert_eq!(
+                    (status, actual),
+                    (Status::Halted, expected),
+                    "optimize = {optimize}, registers = {registers:?}, program = {program:?}"
+                );
+            }
+            checked += 1;
+        }
+        assert!(checked > 1000);
+    }
+}
@@ -0,0 +1,416 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    ops::{Index, IndexMut},
+};
+
+/// Offsets of the 4 orthogonal neighbors, in up, down, left, right order.
+pub const ORTHOGONAL: [(isize, isize); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// Offsets of the 8 neighbors, orthogonal and diagonal, row by row.
+pub const ALL_AROUND: [(isize, isize); 8] = [
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+    (-1, 0),
+    (1, 0),
+    (-1, 1),
+    (0, 1),
+    (1, 1),
+];
+
+fn offset((x, y): (usize, usize), (dx, dy): (isize, isize)) -> Option<(usize, usize)> {
+    Some((x.checked_add_signed(dx)?, y.checked_add_signed(dy)?))
+}
+
+/// A bounded grid stored row by row. Positions are `(x, y)` pairs, with `x`
+/// growing to the right and `y` growing downwards.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Grid<T> {
+    width: usize,
+    height: usize,
+    cells: Vec<T>,
+}
+
+impl<T: Clone> Grid<T> {
+    pub fn new(width: usize, height: usize, value: T) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![value; width * height],
+        }
+    }
+
+    /// Returns a copy rotated by a quarter turn clockwise.
+    pub fn rotated_clockwise(&self) -> Self {
+        Self::from_fn(self.height, self.width, |(x, y)| {
+            self[(y, self.height - 1 - x)].clone()
+        })
+    }
+
+    /// Returns a copy rotated by a quarter turn counterclockwise.
+    pub fn rotated_counterclockwise(&self) -> Self {
+        Self::from_fn(self.height, self.width, |(x, y)| {
+            self[(self.width - 1 - y, x)].clone()
+        })
+    }
+}
+
+impl<T> Grid<T> {
+    pub fn from_fn<F: FnMut((usize, usize)) -> T>(width: usize, height: usize, mut f: F) -> Self {
+        let cells = (0..height)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .map(&mut f)
+            .collect();
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Builds a grid out of cells listed row by row.
+    pub fn from_vec(width: usize, height: usize, cells: Vec<T>) -> Self {
+        assert_eq!(cells.len(), width * height, "Wrong number of cells");
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// Parses one cell per character, one row per line. Shorter lines are
+    /// padded with spaces so that the grid stays rectangular.
+    pub fn parse<F: FnMut(char) -> T>(input: &str, mut f: F) -> Self {
+        let lines = input.lines().collect::<Vec<_>>();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+        let cells = lines
+            .iter()
+            .flat_map(|l| l.chars().chain(std::iter::repeat(' ')).take(width))
+            .map(&mut f)
+            .collect();
+        Self {
+            width,
+            height: lines.len(),
+            cells,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn contains(&self, (x, y): (usize, usize)) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn get(&self, position: (usize, usize)) -> Option<&T> {
+        self.contains(position)
+            .then(|| &self.cells[position.1 * self.width + position.0])
+    }
+
+    pub fn get_mut(&mut self, position: (usize, usize)) -> Option<&mut T> {
+        self.contains(position)
+            .then(|| &mut self.cells[position.1 * self.width + position.0])
+    }
+
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), &T)> {
+        self.positions().zip(self.cells.iter())
+    }
+
+    pub fn find<P: FnMut(&T) -> bool>(&self, mut predicate: P) -> Option<(usize, usize)> {
+        self.iter()
+            .find(|(_, cell)| predicate(cell))
+            .map(|(position, _)| position)
+    }
+
+    pub fn map<U, F: FnMut(&T) -> U>(&self, f: F) -> Grid<U> {
+        Grid {
+            width: self.width,
+            height: self.height,
+            cells: self.cells.iter().map(f).collect(),
+        }
+    }
+
+    /// The position reached moving by `delta`, if still inside the grid.
+    pub fn neighbor(
+        &self,
+        position: (usize, usize),
+        delta: (isize, isize),
+    ) -> Option<(usize, usize)> {
+        offset(position, delta).filter(|p| self.contains(*p))
+    }
+
+    pub fn neighbors4(
+        &self,
+        position: (usize, usize),
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        ORTHOGONAL
+            .iter()
+            .filter_map(move |delta| self.neighbor(position, *delta))
+    }
+
+    pub fn neighbors8(
+        &self,
+        position: (usize, usize),
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        ALL_AROUND
+            .iter()
+            .filter_map(move |delta| self.neighbor(position, *delta))
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[T]> {
+        (0..self.height).map(|y| self.row(y))
+    }
+
+    pub fn row(&self, y: usize) -> &[T] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    /// The cells of column `x` from top to bottom, none if it is outside the
+    /// grid.
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &T> {
+        let height = if x < self.width { self.height } else { 0 };
+        self.cells
+            .iter()
+            .skip(x)
+            // An empty grid has no cells to step over
+            .step_by(self.width.max(1))
+            .take(height)
+    }
+
+    pub fn push_row(&mut self, row: Vec<T>) {
+        if self.height == 0 {
+            self.width = row.len();
+        }
+        assert_eq!(row.len(), self.width, "Wrong row length");
+        self.cells.extend(row);
+        self.height += 1;
+    }
+
+    /// Shifts row `y` right by `n` cells, wrapping around.
+    pub fn rotate_row(&mut self, y: usize, n: usize) {
+        let width = self.width;
+        if width == 0 {
+            return;
+        }
+        self.cells[y * width..(y + 1) * width].rotate_right(n % width);
+    }
+
+    /// Shifts column `x` down by `n` cells, wrapping around.
+    pub fn rotate_column(&mut self, x: usize, n: usize) {
+        let height = self.height;
+        if self.width == 0 || height == 0 {
+            return;
+        }
+        (0..n % height).for_each(|_| {
+            (1..height).rev().for_each(|y| {
+                self.cells
+                    .swap(y * self.width + x, (y - 1) * self.width + x)
+            })
+        });
+    }
+
+    /// Renders one character per cell, one line per row.
+    pub fn render<F: FnMut((usize, usize), &T) -> char>(&self, mut f: F) -> String {
+        self.rows()
+            .enumerate()
+            .map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, cell)| f((x, y), cell))
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+impl<T: From<char>> From<&str> for Grid<T> {
+    fn from(value: &str) -> Self {
+        Self::parse(value, T::from)
+    }
+}
+
+impl<T: Display> Display for Grid<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.rows().enumerate().try_for_each(|(y, row)| {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            row.iter().try_for_each(|cell| write!(f, "{cell}"))
+        })
+    }
+}
+
+impl<T> Index<(usize, usize)> for Grid<T> {
+    type Output = T;
+
+    fn index(&self, position: (usize, usize)) -> &Self::Output {
+        self.get(position)
+            .unwrap_or_else(|| panic!("Position {position:?} is out of the grid"))
+    }
+}
+
+impl<T> IndexMut<(usize, usize)> for Grid<T> {
+    fn index_mut(&mut self, position: (usize, usize)) -> &mut Self::Output {
+        self.get_mut(position)
+            .unwrap_or_else(|| panic!("Position {position:?} is out of the grid"))
+    }
+}
+
+/// A grid that extends without bounds to the right and downwards. Cells are
+/// generated on first access and then cached.
+pub struct LazyGrid<T> {
+    cells: HashMap<(usize, usize), T>,
+    generator: Box<dyn Fn((usize, usize)) -> T>,
+}
+
+impl<T> LazyGrid<T> {
+    pub fn new<F: Fn((usize, usize)) -> T + 'static>(generator: F) -> Self {
+        Self {
+            cells: HashMap::new(),
+            generator: Box::new(generator),
+        }
+    }
+
+    pub fn get(&mut self, position: (usize, usize)) -> &T {
+        self.cells
+            .entry(position)
+            .or_insert_with(|| (self.generator)(position))
+    }
+
+    pub fn neighbor(position: (usize, usize), delta: (isize, isize)) -> Option<(usize, usize)> {
+        offset(position, delta)
+    }
+
+    pub fn neighbors4(position: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        ORTHOGONAL
+            .iter()
+            .filter_map(move |delta| offset(position, *delta))
+    }
+
+    pub fn neighbors8(position: (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        ALL_AROUND
+            .iter()
+            .filter_map(move |delta| offset(position, *delta))
+    }
+
+    /// Copies the `width` x `height` window whose top left corner is
+    /// `origin` into a bounded grid.
+    pub fn window(&mut self, origin: (usize, usize), width: usize, height: usize) -> Grid<T>
+    where
+        T: Clone,
+    {
+        Grid::from_fn(width, height, |(x, y)| {
+            self.get((origin.0 + x, origin.1 + y)).clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod grid_tests {
+    use super::*;
+
+    const INPUT: &str = r#"#..
+.#.
+..#
+##."#;
+
+    #[test]
+    fn test_parse_and_render() {
+        let grid = Grid::<char>::from(INPUT);
+        assert_eq!(grid.width(), 3);
+        assert_eq!(grid.height(), 4);
+        assert_eq!(grid[(1, 1)], '#');
+        assert_eq!(grid.get((3, 0)), None);
+        assert_eq!(grid.to_string(), INPUT);
+        assert_eq!(
+            grid.render(|_, c| if *c == '#' { 'X' } else { ' ' }),
+            "X  \n X \n  X\nXX "
+        );
+
+        let ragged = Grid::<char>::from(" 1\n234");
+        assert_eq!(ragged.to_string(), " 1 \n234");
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let grid = Grid::new(3, 2, 0);
+        assert_eq!(
+            grid.neighbors4((0, 0)).collect::<Vec<_>>(),
+            vec![(0, 1), (1, 0)]
+        );
+        assert_eq!(
+            grid.neighbors4((1, 1)).collect::<Vec<_>>(),
+            vec![(1, 0), (0, 1), (2, 1)]
+        );
+        assert_eq!(
+            grid.neighbors8((2, 1)).collect::<Vec<_>>(),
+            vec![(1, 0), (2, 0), (1, 1)]
+        );
+        assert_eq!(grid.neighbors8((1, 0)).count(), 5);
+        assert_eq!(grid.neighbor((2, 1), (1, 0)), None);
+    }
+
+    #[test]
+    fn test_rows_and_columns() {
+        let grid = Grid::<char>::from(INPUT);
+        assert_eq!(grid.row(3), &['#', '#', '.']);
+        assert_eq!(grid.column(1).collect::<String>(), ".#.#".to_string());
+        assert_eq!(grid.rows().count(), 4);
+
+        let mut grid = Grid::<char>::from("abc\ndef");
+        grid.rotate_row(0, 1);
+        assert_eq!(grid.to_string(), "cab\ndef");
+        grid.rotate_column(2, 3);
+        assert_eq!(grid.to_string(), "caf\ndeb");
+        grid.push_row(vec!['g', 'h', 'i']);
+        assert_eq!(grid.to_string(), "caf\ndeb\nghi");
+
+        let mut grid = Grid::<char>::from("");
+        assert_eq!(grid.column(0).count(), 0);
+        assert_eq!(grid.rows().count(), 0);
+        grid.rotate_row(0, 1);
+        grid.rotate_column(0, 1);
+        assert_eq!(grid.to_string(), "");
+
+        let grid = Grid::<char>::from("abc\ndef");
+        assert_eq!(grid.column(2).collect::<String>(), "cf");
+        assert_eq!(grid.column(3).count(), 0);
+
+        let grid = Grid::<char>::from("\n\n");
+        assert_eq!((grid.width(), grid.height()), (0, 2));
+        assert_eq!(grid.rows().count(), 2);
+        assert_eq!(grid.column(0).count(), 0);
+        assert_eq!(grid.to_string(), "\n");
+    }
+
+    #[test]
+    fn test_rotation() {
+        let grid = Grid::<char>::from("ab\ncd\nef");
+        assert_eq!(grid.rotated_clockwise().to_string(), "eca\nfdb");
+        assert_eq!(grid.rotated_counterclockwise().to_string(), "bdf\nace");
+        assert_eq!(grid.rotated_clockwise().rotated_counterclockwise(), grid);
+    }
+
+    #[test]
+    fn test_lazy_grid() {
+        let mut grid = LazyGrid::new(|(x, y)| x * 10 + y);
+        assert_eq!(*grid.get((3, 4)), 34);
+        assert_eq!(
+            LazyGrid::<usize>::neighbors4((0, 5)).collect::<Vec<_>>(),
+            vec![(0, 4), (0, 6), (1, 5)]
+        );
+        assert_eq!(grid.window((1, 1), 2, 2).to_string(), "1121\n1222");
+    }
+}
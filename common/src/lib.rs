@@ -1,7 +1,12 @@
 use std::{fs, path::PathBuf};
 
+pub mod assembunny;
+pub mod grid;
+pub mod md5;
+pub mod search;
+pub mod tsp;
+
 pub fn read_input(file_name: &str) -> String {
     let path = PathBuf::from(format!("inputs/{file_name}"));
     fs::read_to_string(path).unwrap()
 }
-
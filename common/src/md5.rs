@@ -0,0 +1,180 @@
+use std::fmt::{self, Display};
+
+pub use ::md5::Context;
+
+/// A raw MD5 digest, read as 32 hexadecimal digits or nibbles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Digest(pub [u8; 16]);
+
+impl Digest {
+    pub fn of<T: AsRef<[u8]>>(data: T) -> Self {
+        Self(::md5::compute(data).0)
+    }
+
+    /// The `n`th hexadecimal digit, from 0 to 31.
+    pub fn nibble(&self, n: usize) -> u8 {
+        match n % 2 {
+            0 => self.0[n / 2] >> 4,
+            _ => self.0[n / 2] & 0xf,
+        }
+    }
+
+    pub fn nibbles(&self) -> impl Iterator<Item = u8> + '_ {
+        (0..32).map(|n| self.nibble(n))
+    }
+
+    /// How many hexadecimal digits are 0 before the first one that isn't.
+    pub fn leading_zeros(&self) -> usize {
+        self.nibbles().take_while(|nibble| *nibble == 0).count()
+    }
+
+    /// The digit of the first run of `k` identical digits in a row.
+    pub fn first_run(&self, k: usize) -> Option<u8> {
+        self.runs(k).next()
+    }
+
+    /// Whether `nibble` shows up `k` times in a row somewhere.
+    pub fn has_run(&self, k: usize, nibble: u8) -> bool {
+        self.runs(k).any(|run| run == nibble)
+    }
+
+    /// The digit of every run of at least `k` identical digits, in order.
+    pub fn runs(&self, k: usize) -> impl Iterator<Item = u8> + '_ {
+        let mut length = 0;
+        (0..32).filter_map(move |n| {
+            let nibble = self.nibble(n);
+            length = match n > 0 && self.nibble(n - 1) == nibble {
+                true => length + 1,
+                false => 1,
+            };
+            (length == k).then_some(nibble)
+        })
+    }
+
+    /// Lowercase hexadecimal digits, as hashed again by key stretching.
+    pub fn to_hex(&self) -> [u8; 32] {
+        let mut hex = [0; 32];
+        hex.iter_mut().enumerate().for_each(|(n, digit)| {
+            *digit = b"0123456789abcdef"[self.nibble(n) as usize];
+        });
+        hex
+    }
+
+    /// Hashes the hexadecimal digits `rounds` more times.
+    pub fn stretched(self, rounds: usize) -> Self {
+        (0..rounds).fold(self, |digest, _| Self::of(digest.to_hex()))
+    }
+}
+
+impl From<&Context> for Digest {
+    fn from(value: &Context) -> Self {
+        Self(value.clone().compute().0)
+    }
+}
+
+impl Display for Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.to_hex()
+            .iter()
+            .try_for_each(|digit| write!(f, "{}", *digit as char))
+    }
+}
+
+/// Hashes a salt followed by an index, without hashing the salt again for
+/// every index.
+#[derive(Clone)]
+pub struct Salted {
+    context: Context,
+}
+
+impl Salted {
+    pub fn new(salt: &str) -> Self {
+        let mut context = Context::new();
+        context.consume(salt);
+        Self { context }
+    }
+
+    pub fn digest(&self, index: u64) -> Digest {
+        // Decimal digits of the index, written from the end
+        let mut digits = [0; 20];
+        let mut start = digits.len();
+        let mut rest = index;
+        loop {
+            start -= 1;
+            digits[start] = b'0' + (rest % 10) as u8;
+            rest /= 10;
+            if rest == 0 {
+                break;
+            }
+        }
+        let mut context = self.context.clone();
+        context.consume(&digits[start..]);
+        Digest::from(&context)
+    }
+}
+
+#[cfg(test)]
+mod md5_tests {
+    use parameterized::parameterized;
+
+    use super::*;
+
+    #[test]
+    fn test_hex() {
+        let digest = Digest::of("abc18");
+        assert_eq!(digest.to_string(), format!("{:x}", ::md5::compute("abc18")));
+        assert_eq!(
+            digest.nibbles().collect::<Vec<_>>(),
+            digest
+                .to_string()
+                .chars()
+                .map(|c| c.to_digit(16).unwrap() as u8)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[parameterized(
+        input = { "abc3231929", "abc5017308", "abc5278568", "abc0" },
+        expected = { 5, 5, 5, 0 }
+    )]
+    fn test_leading_zeros(input: &str, expected: usize) {
+        assert_eq!(Digest::of(input).leading_zeros(), expected);
+    }
+
+    #[parameterized(
+        input = { 1, 17, 18, 19, 25, 39, 92 },
+        expected = { None, None, Some(8), None, None, Some(0xe), Some(9) }
+    )]
+    fn test_first_run(input: u64, expected: Option<u8>) {
+        assert_eq!(Salted::new("abc").digest(input).first_run(3), expected);
+    }
+
+    #[test]
+    fn test_runs() {
+        // cc38887a5 contains a single run of three eights
+        let digest = Salted::new("abc").digest(18);
+        assert!(digest.has_run(3, 8));
+        assert!(!digest.has_run(4, 8));
+        assert!(!digest.has_run(3, 0xc));
+        assert!(digest.has_run(2, 0xc));
+        assert!(Salted::new("abc").digest(816).has_run(5, 0xe));
+    }
+
+    #[test]
+    fn test_salted() {
+        let salted = Salted::new("abc");
+        [0, 9, 10, 3231929, u64::MAX].into_iter().for_each(|index| {
+            assert_eq!(salted.digest(index), Digest::of(format!("abc{index}")));
+        });
+    }
+
+    #[test]
+    fn test_stretched() {
+        let digest = Salted::new("abc").digest(0).stretched(2016);
+        assert!(digest.to_string().starts_with("a107ff"));
+        let again = (0..2016).fold(format!("{:x}", ::md5::compute("abc0")), |hex, _| {
+            format!("{:x}", ::md5::compute(hex))
+        });
+        assert_eq!(digest.to_string(), again);
+    }
+}
@@ -0,0 +1,410 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{hash_map::Entry, BinaryHeap, HashMap, VecDeque},
+    hash::Hash,
+};
+
+/// A path found by one of the searches: its total cost and every state from
+/// the start to the goal, both included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Path<S> {
+    pub cost: usize,
+    pub states: Vec<S>,
+}
+
+impl<S> Path<S> {
+    pub fn goal(&self) -> &S {
+        self.states.last().unwrap()
+    }
+}
+
+fn rebuild_path<S: Clone>(states: &[S], parents: &[Option<usize>], goal: usize) -> Vec<S> {
+    let mut path = vec![];
+    let mut current = Some(goal);
+    while let Some(index) = current {
+        path.push(states[index].clone());
+        current = parents[index];
+    }
+    path.reverse();
+    path
+}
+
+/// Breadth-first search, for graphs where every move costs 1.
+pub fn bfs<S, N, I, G>(start: S, mut neighbors: N, mut is_goal: G) -> Option<Path<S>>
+where
+    S: Clone + Eq + Hash,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = S>,
+    G: FnMut(&S) -> bool,
+{
+    let mut states = vec![start.clone()];
+    let mut parents = vec![None];
+    let mut depths = vec![0];
+    let mut seen = HashMap::from([(start, 0)]);
+    let mut queue = VecDeque::from([0]);
+    while let Some(current) = queue.pop_front() {
+        if is_goal(&states[current]) {
+            return Some(Path {
+                cost: depths[current],
+                states: rebuild_path(&states, &parents, current),
+            });
+        }
+        for next in neighbors(&states[current]) {
+            if let Entry::Vacant(entry) = seen.entry(next.clone()) {
+                entry.insert(states.len());
+                queue.push_back(states.len());
+                states.push(next);
+                parents.push(Some(current));
+                depths.push(depths[current] + 1);
+            }
+        }
+    }
+    None
+}
+
+/// Breadth-first flood fill from `start`: the number of moves needed to reach
+/// every state at most `max_cost` moves away.
+pub fn bfs_distances<S, N, I>(start: S, max_cost: usize, mut neighbors: N) -> HashMap<S, usize>
+where
+    S: Clone + Eq + Hash,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = S>,
+{
+    let mut distances = HashMap::from([(start.clone(), 0)]);
+    let mut queue = VecDeque::from([(start, 0)]);
+    while let Some((current, cost)) = queue.pop_front() {
+        if cost == max_cost {
+            continue;
+        }
+        for next in neighbors(&current) {
+            if let Entry::Vacant(entry) = distances.entry(next.clone()) {
+                entry.insert(cost + 1);
+                queue.push_back((next, cost + 1));
+            }
+        }
+    }
+    distances
+}
+
+/// Dijkstra's algorithm, for graphs whose moves have a non-negative cost.
+pub fn dijkstra<S, N, I, G>(start: S, neighbors: N, is_goal: G) -> Option<Path<S>>
+where
+    S: Clone + Eq + Hash,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, usize)>,
+    G: FnMut(&S) -> bool,
+{
+    astar(start, neighbors, |_| 0, is_goal)
+}
+
+/// A* search. The heuristic must never overestimate the remaining cost,
+/// otherwise the path found is not guaranteed to be the cheapest one.
+///
+/// Among states with the same estimate the deepest is expanded first, which
+/// keeps the frontier small when the heuristic is tight.
+pub fn astar<S, N, I, H, G>(start: S, neighbors: N, heuristic: H, is_goal: G) -> Option<Path<S>>
+where
+    S: Clone + Eq + Hash,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, usize)>,
+    H: FnMut(&S) -> usize,
+    G: FnMut(&S) -> bool,
+{
+    astar_by_key(start, S::clone, neighbors, heuristic, is_goal)
+}
+
+struct Candidate<S> {
+    priority: usize,
+    cost: usize,
+    index: usize,
+    state: S,
+}
+
+impl<S> PartialEq for Candidate<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S> Eq for Candidate<S> {}
+
+impl<S> PartialOrd for Candidate<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S> Ord for Candidate<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (Reverse(self.priority), self.cost).cmp(&(Reverse(other.priority), other.cost))
+    }
+}
+
+/// A* search for states that are expensive to keep around. Visited states
+/// are only remembered by their `key`, full states live on the frontier
+/// until they are expanded, and the path is returned as a list of keys.
+pub fn astar_by_key<S, K, F, N, I, H, G>(
+    start: S,
+    mut key: F,
+    mut neighbors: N,
+    mut heuristic: H,
+    mut is_goal: G,
+) -> Option<Path<K>>
+where
+    K: Clone + Eq + Hash,
+    F: FnMut(&S) -> K,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, usize)>,
+    H: FnMut(&S) -> usize,
+    G: FnMut(&S) -> bool,
+{
+    let start_key = key(&start);
+    let mut keys = vec![start_key.clone()];
+    let mut parents = vec![None];
+    let mut costs = vec![0];
+    let mut seen = HashMap::from([(start_key, 0)]);
+    let mut queue = BinaryHeap::from([Candidate {
+        priority: heuristic(&start),
+        cost: 0,
+        index: 0,
+        state: start,
+    }]);
+    while let Some(Candidate {
+        cost,
+        index: current,
+        state,
+        ..
+    }) = queue.pop()
+    {
+        if cost > costs[current] {
+            continue;
+        }
+        if is_goal(&state) {
+            return Some(Path {
+                cost,
+                states: rebuild_path(&keys, &parents, current),
+            });
+        }
+        for (next, move_cost) in neighbors(&state) {
+            let next_cost = cost + move_cost;
+            let index = match seen.entry(key(&next)) {
+                Entry::Occupied(entry) => {
+                    let index = *entry.get();
+                    if next_cost >= costs[index] {
+                        continue;
+                    }
+                    parents[index] = Some(current);
+                    costs[index] = next_cost;
+                    index
+                }
+                Entry::Vacant(entry) => {
+                    keys.push(entry.key().clone());
+                    entry.insert(keys.len() - 1);
+                    parents.push(Some(current));
+                    costs.push(next_cost);
+                    keys.len() - 1
+                }
+            };
+            queue.push(Candidate {
+                priority: next_cost + heuristic(&next),
+                cost: next_cost,
+                index,
+                state: next,
+            });
+        }
+    }
+    None
+}
+
+enum Deepening {
+    Found(usize),
+    Exceeded(usize),
+    Exhausted,
+}
+
+fn deepen<S, N, I, H, G>(
+    path: &mut Vec<S>,
+    cost: usize,
+    bound: usize,
+    neighbors: &mut N,
+    heuristic: &mut H,
+    is_goal: &mut G,
+) -> Deepening
+where
+    S: Eq,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, usize)>,
+    H: FnMut(&S) -> usize,
+    G: FnMut(&S) -> bool,
+{
+    let current = path.last().unwrap();
+    let estimate = cost + heuristic(current);
+    if estimate > bound {
+        return Deepening::Exceeded(estimate);
+    }
+    if is_goal(current) {
+        return Deepening::Found(cost);
+    }
+    let mut result = Deepening::Exhausted;
+    for (next, move_cost) in neighbors(current) {
+        if path.contains(&next) {
+            continue;
+        }
+        path.push(next);
+        match deepen(path, cost + move_cost, bound, neighbors, heuristic, is_goal) {
+            found @ Deepening::Found(_) => return found,
+            Deepening::Exceeded(estimate) => {
+                result = match result {
+                    Deepening::Exceeded(min) if min <= estimate => result,
+                    _ => Deepening::Exceeded(estimate),
+                }
+            }
+            Deepening::Exhausted => {}
+        }
+        path.pop();
+    }
+    result
+}
+
+/// Iterative deepening A*. It only keeps the current path in memory, so it
+/// suits huge graphs where the other searches would run out of memory, and
+/// it does not need the states to be hashable.
+pub fn idastar<S, N, I, H, G>(
+    start: S,
+    mut neighbors: N,
+    mut heuristic: H,
+    mut is_goal: G,
+) -> Option<Path<S>>
+where
+    S: Eq,
+    N: FnMut(&S) -> I,
+    I: IntoIterator<Item = (S, usize)>,
+    H: FnMut(&S) -> usize,
+    G: FnMut(&S) -> bool,
+{
+    let mut bound = heuristic(&start);
+    let mut path = vec![start];
+    loop {
+        match deepen(
+            &mut path,
+            0,
+            bound,
+            &mut neighbors,
+            &mut heuristic,
+            &mut is_goal,
+        ) {
+            Deepening::Found(cost) => return Some(Path { cost, states: path }),
+            Deepening::Exceeded(estimate) => bound = estimate,
+            Deepening::Exhausted => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod search_tests {
+    use super::*;
+
+    const MAZE: &str = r#"#########
+#S..#...#
+#.#.#.#.#
+#.#...#.#
+#.#####.#
+#......G#
+#########"#;
+
+    fn find(c: char) -> (usize, usize) {
+        MAZE.lines()
+            .enumerate()
+            .find_map(|(y, row)| row.find(c).map(|x| (x, y)))
+            .unwrap()
+    }
+
+    fn open_neighbors(&(x, y): &(usize, usize)) -> Vec<(usize, usize)> {
+        let rows = MAZE.lines().collect::<Vec<_>>();
+        [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+            .into_iter()
+            .filter(|&(x, y)| rows[y].as_bytes()[x] != b'#')
+            .collect()
+    }
+
+    fn manhattan(&(x, y): &(usize, usize)) -> usize {
+        let (goal_x, goal_y) = find('G');
+        x.abs_diff(goal_x) + y.abs_diff(goal_y)
+    }
+
+    #[test]
+    fn test_bfs() {
+        let goal = find('G');
+        let path = bfs(find('S'), open_neighbors, |p| *p == goal).unwrap();
+        assert_eq!(path.cost, 10);
+        assert_eq!(path.states.len(), 11);
+        assert_eq!(path.states[0], find('S'));
+        assert_eq!(*path.goal(), goal);
+        assert!(path
+            .states
+            .windows(2)
+            .all(|w| open_neighbors(&w[0]).contains(&w[1])));
+        assert_eq!(bfs(find('S'), open_neighbors, |p| *p == (2, 2)), None);
+    }
+
+    #[test]
+    fn test_bfs_distances() {
+        let distances = bfs_distances(find('S'), usize::MAX, open_neighbors);
+        assert_eq!(distances.len(), 24);
+        assert_eq!(distances[&find('S')], 0);
+        assert_eq!(distances[&find('G')], 10);
+        assert!(!distances.contains_key(&(2, 2)));
+        let near = bfs_distances(find('S'), 3, open_neighbors);
+        assert_eq!(near.len(), 7);
+        assert!(near.values().all(|d| *d <= 3));
+    }
+
+    #[test]
+    fn test_weighted_searches_agree() {
+        let goal = find('G');
+        // Moving down costs 3, any other move costs 1
+        let weighted = |p: &(usize, usize)| {
+            let p = *p;
+            open_neighbors(&p)
+                .into_iter()
+                .map(move |n| (n, if n.1 > p.1 { 3 } else { 1 }))
+        };
+        let expected = dijkstra(find('S'), weighted, |p| *p == goal).unwrap();
+        assert_eq!(expected.cost, 18);
+        let path = astar(find('S'), weighted, manhattan, |p| *p == goal).unwrap();
+        assert_eq!(path, expected);
+        let path = idastar(find('S'), weighted, manhattan, |p| *p == goal).unwrap();
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn test_astar_by_key() {
+        let goal = find('G');
+        // The state drags along how it got there, which is not part of its
+        // identity
+        let path = astar_by_key(
+            (find('S'), String::new()),
+            |(p, _)| *p,
+            |(p, trail)| {
+                open_neighbors(p)
+                    .into_iter()
+                    .map(|n| ((n, format!("{trail}{n:?}")), 1))
+                    .collect::<Vec<_>>()
+            },
+            |(p, _)| manhattan(p),
+            |(p, _)| *p == goal,
+        )
+        .unwrap();
+        let expected = bfs(find('S'), open_neighbors, |p| *p == goal).unwrap();
+        assert_eq!(path, expected);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let goal = (2, 2);
+        let weighted = |p: &(usize, usize)| open_neighbors(p).into_iter().map(|n| (n, 1));
+        assert_eq!(dijkstra(find('S'), weighted, |p| *p == goal), None);
+        assert_eq!(astar(find('S'), weighted, |_| 0, |p| *p == goal), None);
+        assert_eq!(idastar(find('S'), weighted, |_| 0, |p| *p == goal), None);
+    }
+}
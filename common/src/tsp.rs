@@ -0,0 +1,471 @@
+/// Whether a tour has to come back to where it started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Route {
+    Open,
+    Closed,
+}
+
+/// An order in which to visit every node, with its total cost.
+///
+/// `lower_bound` is the cost no tour can beat: it equals `cost` when the
+/// tour is known to be optimal, otherwise it bounds how far from optimal the
+/// heuristic tour may be.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tour {
+    pub order: Vec<usize>,
+    pub cost: usize,
+    pub lower_bound: usize,
+}
+
+impl Tour {
+    pub fn is_optimal(&self) -> bool {
+        self.cost == self.lower_bound
+    }
+
+    /// How much more the tour may cost than the optimal one, relative to the
+    /// lower bound.
+    pub fn gap(&self) -> f64 {
+        match self.lower_bound {
+            0 if self.cost == 0 => 0.0,
+            0 => f64::INFINITY,
+            bound => (self.cost - bound) as f64 / bound as f64,
+        }
+    }
+}
+
+/// Solves the travelling salesman problem over a distance matrix, where
+/// `distances[from][to]` is the cost of going from `from` to `to`.
+///
+/// Up to `exact_limit` nodes the Held-Karp dynamic programming finds an
+/// optimal tour; above that a nearest neighbour tour improved by 2-opt is
+/// returned, along with a lower bound from a minimum spanning tree.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    route: Route,
+    start: Option<usize>,
+    end: Option<usize>,
+    exact_limit: usize,
+}
+
+impl Solver {
+    pub fn new(route: Route) -> Self {
+        Self {
+            route,
+            start: None,
+            end: None,
+            exact_limit: 16,
+        }
+    }
+
+    pub fn start(mut self, node: usize) -> Self {
+        self.start = Some(node);
+        self
+    }
+
+    /// Only meaningful for open routes, a closed route ends at its start.
+    /// An open route ending at its start is solved as a closed one, with the
+    /// start repeated at the end of the order.
+    pub fn end(mut self, node: usize) -> Self {
+        self.end = Some(node);
+        self
+    }
+
+    pub fn exact_limit(mut self, nodes: usize) -> Self {
+        self.exact_limit = nodes;
+        self
+    }
+
+    pub fn solve(&self, distances: &[Vec<usize>]) -> Tour {
+        let n = distances.len();
+        if n <= 1 {
+            let order = (0..n).collect();
+            return Tour {
+                order,
+                cost: 0,
+                lower_bound: 0,
+            };
+        }
+        if self.route == Route::Open && self.start.is_some() && self.start == self.end {
+            // Ending where it started is a closed route, walked back to the start
+            let mut closed = self.clone();
+            closed.route = Route::Closed;
+            closed.end = None;
+            let mut tour = closed.solve(distances);
+            tour.order.push(tour.order[0]);
+            return tour;
+        }
+        if n <= self.exact_limit {
+            self.held_karp(distances)
+        } else {
+            let order = self.two_opt(distances, self.nearest_neighbour(distances));
+            Tour {
+                cost: self.cost(distances, &order),
+                lower_bound: self.lower_bound(distances),
+                order,
+            }
+        }
+    }
+
+    fn fixed_end(&self) -> Option<usize> {
+        self.end.filter(|_| self.route == Route::Open)
+    }
+
+    fn starts(&self, n: usize) -> Vec<usize> {
+        match (self.start, self.route) {
+            (Some(start), _) => vec![start],
+            // Any node works as the start of a cycle
+            (None, Route::Closed) => vec![0],
+            (None, Route::Open) => (0..n).filter(|node| self.end != Some(*node)).collect(),
+        }
+    }
+
+    fn cost(&self, distances: &[Vec<usize>], order: &[usize]) -> usize {
+        let path = order
+            .windows(2)
+            .map(|w| distances[w[0]][w[1]])
+            .sum::<usize>();
+        match self.route {
+            Route::Open => path,
+            Route::Closed => path + distances[*order.last().unwrap()][order[0]],
+        }
+    }
+
+    fn held_karp(&self, distances: &[Vec<usize>]) -> Tour {
+        let n = distances.len();
+        let full = (1 << n) - 1;
+        // best[mask * n + last]: cheapest path from a start through the nodes
+        // in `mask`, ending at `last`
+        let mut best = vec![usize::MAX; (1 << n) * n];
+        let mut parents = vec![usize::MAX; (1 << n) * n];
+        self.starts(n)
+            .into_iter()
+            .for_each(|start| best[(1 << start) * n + start] = 0);
+        (1..=full).for_each(|mask: usize| {
+            (0..n)
+                .filter(|last| mask & (1 << last) != 0)
+                .for_each(|last| {
+                    let cost = best[mask * n + last];
+                    if cost == usize::MAX {
+                        return;
+                    }
+                    (0..n)
+                        .filter(|next| mask & (1 << next) == 0)
+                        // The fixed end can only be the last node visited
+                        .filter(|next| {
+                            self.fixed_end() != Some(*next) || mask | (1 << next) == full
+                        })
+                        .for_each(|next| {
+                            let index = (mask | (1 << next)) * n + next;
+                            let next_cost = cost + distances[last][next];
+                            if next_cost < best[index] {
+                                best[index] = next_cost;
+                                parents[index] = last;
+                            }
+                        })
+                })
+        });
+        let (cost, mut last) = (0..n)
+            .filter(|last| self.fixed_end().is_none_or(|end| end == *last))
+            .filter(|last| best[full * n + last] != usize::MAX)
+            .map(|last| {
+                let back = match self.route {
+                    Route::Open => 0,
+                    // Closed routes always start from a single node
+                    Route::Closed => distances[last][self.start.unwrap_or(0)],
+                };
+                (best[full * n + last] + back, last)
+            })
+            .min()
+            .unwrap();
+        let mut order = vec![];
+        let mut mask = full;
+        while last != usize::MAX {
+            order.push(last);
+            let parent = parents[mask * n + last];
+            mask &= !(1 << last);
+            last = parent;
+        }
+        order.reverse();
+        Tour {
+            order,
+            cost,
+            lower_bound: cost,
+        }
+    }
+
+    /// Where the nearest neighbour tour starts. Without a fixed start, an
+    /// open route begins at the node furthest from any other one, as that
+    /// outlier is cheapest to visit at an end.
+    fn first_node(&self, distances: &[Vec<usize>]) -> usize {
+        let n = distances.len();
+        match (self.start, self.route) {
+            (Some(start), _) => start,
+            (None, Route::Closed) => 0,
+            (None, Route::Open) => (0..n)
+                .filter(|node| self.end != Some(*node))
+                .max_by_key(|node| {
+                    (0..n)
+                        .filter(|other| other != node)
+                        .map(|other| distances[*node][other])
+                        .min()
+                })
+                .unwrap(),
+        }
+    }
+
+    fn nearest_neighbour(&self, distances: &[Vec<usize>]) -> Vec<usize> {
+        let n = distances.len();
+        let start = self.first_node(distances);
+        let mut order = vec![start];
+        let mut visited = vec![false; n];
+        visited[start] = true;
+        if let Some(end) = self.fixed_end() {
+            visited[end] = true;
+        }
+        while let Some(next) = (0..n)
+            .filter(|next| !visited[*next])
+            .min_by_key(|next| distances[*order.last().unwrap()][*next])
+        {
+            visited[next] = true;
+            order.push(next);
+        }
+        if let Some(end) = self.fixed_end() {
+            order.push(end);
+        }
+        order
+    }
+
+    /// How much reversing `order[i..=j]` changes the cost of the tour: only
+    /// the edges at both ends of the segment change, plus the edges inside it
+    /// when going backwards costs something else.
+    fn reversal_delta(
+        &self,
+        distances: &[Vec<usize>],
+        order: &[usize],
+        (i, j): (usize, usize),
+        symmetric: bool,
+    ) -> isize {
+        let n = order.len();
+        let edge = |from: Option<usize>, to: Option<usize>| match (from, to) {
+            (Some(from), Some(to)) => distances[from][to] as isize,
+            _ => 0,
+        };
+        let before = i.checked_sub(1).map(|k| order[k]);
+        let after = match (j + 1 < n, self.route) {
+            (true, _) => Some(order[j + 1]),
+            (false, Route::Closed) => Some(order[0]),
+            (false, Route::Open) => None,
+        };
+        let (a, b) = (Some(order[i]), Some(order[j]));
+        let boundary = edge(before, b) + edge(a, after) - edge(before, a) - edge(b, after);
+        let inside = match symmetric {
+            true => 0,
+            false => order[i..=j]
+                .windows(2)
+                .map(|w| distances[w[1]][w[0]] as isize - distances[w[0]][w[1]] as isize)
+                .sum(),
+        };
+        boundary + inside
+    }
+
+    /// Reverses segments of the tour as long as that makes it cheaper,
+    /// without moving the fixed start and end.
+    fn two_opt(&self, distances: &[Vec<usize>], mut order: Vec<usize>) -> Vec<usize> {
+        let n = order.len();
+        let first = usize::from(self.start.is_some() || self.route == Route::Closed);
+        let last = n - 1 - usize::from(self.fixed_end().is_some());
+        let symmetric = (0..n).all(|a| (0..a).all(|b| distances[a][b] == distances[b][a]));
+        let mut improved = true;
+        while improved {
+            improved = false;
+            for i in first..last {
+                for j in i + 1..=last {
+                    if self.reversal_delta(distances, &order, (i, j), symmetric) < 0 {
+                        order[i..=j].reverse();
+                        improved = true;
+                    }
+                }
+            }
+        }
+        order
+    }
+
+    /// Weight of a minimum spanning tree, ignoring the direction of edges.
+    /// Every open path is a spanning tree, and removing an edge from a
+    /// closed tour leaves one, so no tour can cost less.
+    fn lower_bound(&self, distances: &[Vec<usize>]) -> usize {
+        let n = distances.len();
+        let edge = |a: usize, b: usize| distances[a][b].min(distances[b][a]);
+        let mut in_tree = vec![false; n];
+        let mut cheapest = vec![usize::MAX; n];
+        cheapest[0] = 0;
+        (0..n)
+            .map(|_| {
+                let node = (0..n)
+                    .filter(|node| !in_tree[*node])
+                    .min_by_key(|node| cheapest[*node])
+                    .unwrap();
+                in_tree[node] = true;
+                (0..n).for_each(|other| cheapest[other] = cheapest[other].min(edge(node, other)));
+                cheapest[node]
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tsp_tests {
+    use parameterized::parameterized;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+
+    // Five points on a line, at 0, 1, 3, 6 and 10
+    fn line() -> Vec<Vec<usize>> {
+        let positions = [0usize, 1, 3, 6, 10];
+        positions
+            .iter()
+            .map(|a| positions.iter().map(|b| a.abs_diff(*b)).collect())
+            .collect()
+    }
+
+    fn random_points(n: usize, seed: u64) -> Vec<Vec<usize>> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let points = (0..n)
+            .map(|_| (rng.gen_range(0..100usize), rng.gen_range(0..100usize)))
+            .collect::<Vec<_>>();
+        points
+            .iter()
+            .map(|(ax, ay)| {
+                points
+                    .iter()
+                    .map(|(bx, by)| ax.abs_diff(*bx) + ay.abs_diff(*by))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn brute_force(solver: &Solver, distances: &[Vec<usize>]) -> usize {
+        fn permute(items: &mut Vec<usize>, k: usize, result: &mut Vec<Vec<usize>>) {
+            if k == items.len() {
+                result.push(items.clone());
+            }
+            (k..items.len()).for_each(|i| {
+                items.swap(k, i);
+                permute(items, k + 1, result);
+                items.swap(k, i);
+            });
+        }
+        let mut orders = vec![];
+        permute(&mut (0..distances.len()).collect(), 0, &mut orders);
+        orders
+            .iter()
+            .filter(|order| solver.start.is_none_or(|start| order[0] == start))
+            .filter(|order| solver.end.is_none_or(|end| *order.last().unwrap() == end))
+            .map(|order| solver.cost(distances, order))
+            .min()
+            .unwrap()
+    }
+
+    #[parameterized(
+        route = { Route::Open, Route::Open, Route::Open, Route::Open, Route::Closed, Route::Closed },
+        start = { None, Some(2), Some(2), Some(2), None, Some(3) },
+        end = { None, None, Some(0), Some(2), None, None },
+        expected = { 10, 13, 17, 20, 20, 20 }
+    )]
+    fn test_exact(route: Route, start: Option<usize>, end: Option<usize>, expected: usize) {
+        let mut solver = Solver::new(route);
+        if let Some(start) = start {
+            solver = solver.start(start);
+        }
+        if let Some(end) = end {
+            solver = solver.end(end);
+        }
+        let tour = solver.solve(&line());
+        assert_eq!(tour.cost, expected);
+        assert!(tour.is_optimal());
+        assert_eq!(solver.cost(&line(), &tour.order), expected);
+        let mut visited = tour.order.clone();
+        visited.sort();
+        visited.dedup();
+        assert_eq!(visited, vec![0, 1, 2, 3, 4]);
+        assert!(start.is_none_or(|start| tour.order[0] == start));
+        assert!(end.is_none_or(|end| *tour.order.last().unwrap() == end));
+    }
+
+    #[test]
+    fn test_exact_matches_brute_force() {
+        (0..10).for_each(|seed| {
+            let distances = random_points(7, seed);
+            [
+                Solver::new(Route::Open),
+                Solver::new(Route::Open).start(3),
+                Solver::new(Route::Open).start(1).end(5),
+                Solver::new(Route::Closed),
+            ]
+            .iter()
+            .for_each(|solver| {
+                let tour = solver.solve(&distances);
+                assert_eq!(tour.cost, brute_force(solver, &distances));
+                assert_eq!(tour.cost, solver.cost(&distances, &tour.order));
+            })
+        });
+    }
+
+    #[test]
+    fn test_heuristic() {
+        (0..10).for_each(|seed| {
+            let distances = random_points(9, seed);
+            [
+                Solver::new(Route::Open),
+                Solver::new(Route::Open).start(2).end(4),
+                Solver::new(Route::Closed),
+            ]
+            .into_iter()
+            .for_each(|solver| {
+                let optimal = solver.solve(&distances);
+                let tour = solver.clone().exact_limit(4).solve(&distances);
+                assert_eq!(tour.order.len(), 9);
+                assert_eq!(tour.cost, solver.cost(&distances, &tour.order));
+                assert!(tour.lower_bound <= optimal.cost);
+                assert!(tour.cost >= optimal.cost);
+                assert!(tour.gap() >= 0.0);
+                assert!(solver.start.is_none_or(|start| tour.order[0] == start));
+                assert!(solver
+                    .end
+                    .is_none_or(|end| *tour.order.last().unwrap() == end));
+            })
+        });
+    }
+
+    #[test]
+    fn test_reversal_delta() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let asymmetric = (0..8)
+            .map(|_| (0..8).map(|_| rng.gen_range(1..50usize)).collect())
+            .collect::<Vec<Vec<_>>>();
+        [(random_points(8, 3), true), (asymmetric, false)]
+            .iter()
+            .for_each(|(distances, symmetric)| {
+                [Solver::new(Route::Open), Solver::new(Route::Closed)]
+                    .iter()
+                    .for_each(|solver| {
+                        let order = (0..8).collect::<Vec<_>>();
+                        let cost = solver.cost(distances, &order) as isize;
+                        // Closed routes keep their start in place
+                        let first = usize::from(solver.route == Route::Closed);
+                        (first..7).for_each(|i| {
+                            (i + 1..8).for_each(|j| {
+                                let mut reversed = order.clone();
+                                reversed[i..=j].reverse();
+                                let delta = solver.cost(distances, &reversed) as isize - cost;
+                                assert_eq!(
+                                    solver.reversal_delta(distances, &order, (i, j), *symmetric),
+                                    delta
+                                );
+                            })
+                        })
+                    })
+            });
+    }
+}
@@ -1,7 +1,4 @@
-use core::panic;
-use std::{char, isize};
-
-use common::read_input;
+use common::{grid::Grid, read_input};
 
 #[derive(Debug)]
 struct Key {
@@ -12,76 +9,30 @@ struct Key {
     value: char,
 }
 
-impl Key {
-    fn from(input: [[Option<char>; 3]; 3]) -> Self {
-        let value = input[1][1].unwrap();
-        let up = input[0][1];
-        let right = input[1][2];
-        let down = input[2][1];
-        let left = input[1][0];
-        Self {
-            value,
-            up,
-            right,
-            down,
-            left,
-        }
-    }
-}
-
 #[derive(Debug)]
 struct Keypad {
     keys: Vec<Key>,
 }
 
 impl Keypad {
-    fn get_surrounding_matrix(
-        grid: &[Vec<char>],
-        row: usize,
-        col: usize,
-    ) -> [[Option<char>; 3]; 3] {
-        let mut matrix = [[None; 3]; 3];
-
-        for di in 0..3 {
-            for dj in 0..3 {
-                let ni = row as isize + di as isize - 1;
-                let nj = col as isize + dj as isize - 1;
-
-                if ni >= 0
-                    && (ni as usize) < grid.len()
-                    && nj >= 0
-                    && (nj as usize) < grid[ni as usize].len()
-                {
-                    matrix[di][dj] = match grid[ni as usize][nj as usize] {
-                        ' ' => None,
-                        c => Some(c),
-                    };
-                }
-            }
-        }
-
-        matrix
-    }
-
     fn from_str(input: &str) -> Self {
-        let grid: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
-        let keys = (0..grid.len())
-            .flat_map(|i| {
-                let value = grid.clone();
-                (0..grid[i].len())
-                    .map(move |j| {
-                        if !value[i][j].is_whitespace() {
-                            let matrix = Keypad::get_surrounding_matrix(&value, i, j);
-                            Some(Key::from(matrix))
-                        } else {
-                            None
-                        }
-                    })
-                    .filter(|c| c.is_some())
-                    .map(|c| c.unwrap())
-                    .collect::<Vec<_>>()
+        let grid = Grid::<char>::from(input);
+        let key_at = |position: (usize, usize), delta: (isize, isize)| {
+            grid.neighbor(position, delta)
+                .map(|p| grid[p])
+                .filter(|c| !c.is_whitespace())
+        };
+        let keys = grid
+            .iter()
+            .filter(|(_, value)| !value.is_whitespace())
+            .map(|(position, value)| Key {
+                value: *value,
+                up: key_at(position, (0, -1)),
+                right: key_at(position, (1, 0)),
+                down: key_at(position, (0, 1)),
+                left: key_at(position, (-1, 0)),
             })
-            .collect::<Vec<_>>();
+            .collect();
         Self { keys }
     }
 
@@ -112,12 +63,7 @@ impl Keypad {
     fn get_code(&self, input: &str) -> String {
         input
             .lines()
-            .map(|line| {
-                line.trim()
-                    .chars()
-                    .map(|c| Move::from(c))
-                    .collect::<Vec<_>>()
-            })
+            .map(|line| line.trim().chars().map(Move::from).collect::<Vec<_>>())
             .fold(String::new(), |mut code, movements| {
                 let next_digit = self.move_from(code.chars().last().unwrap_or('5'), &movements);
                 code.push(next_digit);
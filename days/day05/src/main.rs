@@ -1,52 +1,237 @@
-fn find_hashes<T>(input: &str, n_zeroes: usize, n_hashes: usize, is_valid: T) -> Vec<String>
+use std::{
+    collections::BTreeMap,
+    env,
+    io::{self, IsTerminal, Write},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use common::md5::{Digest, Salted};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+/// Indices every worker checks at once.
+const CHUNK: u64 = 4096;
+
+/// How far the search went: every index below `checked` has been looked at,
+/// and `found` are the hashes kept so far, in index order.
+struct Progress<'a> {
+    checked: u64,
+    found: &'a [Digest],
+}
+
+/// Chunks searched but not merged yet, by their number, and what they added
+/// up to so far.
+struct Merge<P> {
+    next_chunk: u64,
+    pending: BTreeMap<u64, Vec<Digest>>,
+    found: Vec<Digest>,
+    progress: P,
+}
+
+/// Hashes `input` followed by increasing indices on `workers` threads, each
+/// one taking the next chunk of indices. Chunks are merged in order, so
+/// `is_valid` sees the hashes kept before in the same order as if they were
+/// checked one by one.
+fn find_hashes<T, P>(
+    input: &str,
+    n_zeroes: usize,
+    n_hashes: usize,
+    workers: usize,
+    is_valid: T,
+    progress: P,
+) -> Vec<Digest>
 where
-    T: Fn(&str, &[String]) -> bool,
+    T: Fn(&Digest, &[Digest]) -> bool + Sync,
+    P: FnMut(&Progress) + Send,
 {
-    let mut current = 0;
-    let mut found_hashes = vec![];
-    while found_hashes.len() < n_hashes {
-        let digest = md5::compute(format!("{input}{current}"));
-        let hex = format!("{:x}", digest);
-        if hex.starts_with(&"0".repeat(n_zeroes)) && is_valid(&hex, &found_hashes) {
-            found_hashes.push(hex);
-        };
-        current += 1;
-    }
-    found_hashes
+    let salted = Salted::new(input);
+    let next_chunk = AtomicU64::new(0);
+    let done = AtomicBool::new(n_hashes == 0);
+    let merge = Mutex::new(Merge {
+        next_chunk: 0,
+        pending: BTreeMap::new(),
+        found: vec![],
+        progress,
+    });
+    thread::scope(|s| {
+        (0..workers.max(1)).for_each(|_| {
+            s.spawn(|| {
+                while !done.load(Ordering::SeqCst) {
+                    let chunk = next_chunk.fetch_add(1, Ordering::SeqCst);
+                    let hits = (chunk * CHUNK..(chunk + 1) * CHUNK)
+                        .map(|index| salted.digest(index))
+                        .filter(|digest| digest.leading_zeros() >= n_zeroes)
+                        .collect::<Vec<_>>();
+                    let mut merge = merge.lock().unwrap();
+                    merge.pending.insert(chunk, hits);
+                    while !done.load(Ordering::SeqCst) {
+                        let next = merge.next_chunk;
+                        let Some(hits) = merge.pending.remove(&next) else {
+                            break;
+                        };
+                        hits.into_iter().for_each(|digest| {
+                            if merge.found.len() < n_hashes && is_valid(&digest, &merge.found) {
+                                merge.found.push(digest);
+                            }
+                        });
+                        merge.next_chunk += 1;
+                        let Merge {
+                            next_chunk,
+                            found,
+                            progress,
+                            ..
+                        } = &mut *merge;
+                        progress(&Progress {
+                            checked: *next_chunk * CHUNK,
+                            found,
+                        });
+                        if found.len() == n_hashes {
+                            done.store(true, Ordering::SeqCst);
+                        }
+                    }
+                }
+            });
+        });
+    });
+    merge.into_inner().unwrap().found
+}
+
+fn workers() -> usize {
+    thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+fn hex_digit(nibble: u8) -> char {
+    char::from_digit(nibble as u32, 16).unwrap()
 }
 
-fn find_password(input: &str) -> String {
-    let mut hashes = find_hashes(input, 5, 8, |_, _| true);
-    hashes
+/// Characters of the password known from the hashes found so far.
+type Slots = [Option<char>; 8];
+
+/// The sixth digit of every hash, in order.
+fn first_slots(found: &[Digest]) -> Slots {
+    let mut slots = [None; 8];
+    slots
         .iter_mut()
-        .map(|hash| hash.remove(5))
-        .collect::<String>()
+        .zip(found)
+        .for_each(|(slot, hash)| *slot = Some(hex_digit(hash.nibble(5))));
+    slots
 }
 
-fn find_second_password(input: &str) -> String {
-    let hashes = find_hashes(input, 5, 8, |s, h| {
-        let c = s.as_bytes()[5] as char;
-        match c {
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' => {
-                h.iter().all(|hash| hash.chars().nth(5).unwrap() != c)
-            }
-            _ => false,
+/// The seventh digit of every hash, where its sixth digit says.
+fn second_slots(found: &[Digest]) -> Slots {
+    found.iter().fold([None; 8], |mut slots, hash| {
+        slots[hash.nibble(5) as usize] = Some(hex_digit(hash.nibble(6)));
+        slots
+    })
+}
+
+fn password(slots: Slots) -> String {
+    slots.iter().map(|slot| slot.unwrap_or('_')).collect()
+}
+
+fn find_password<P: FnMut(&Progress) + Send>(input: &str, progress: P) -> String {
+    let hashes = find_hashes(input, 5, 8, workers(), |_, _| true, progress);
+    password(first_slots(&hashes))
+}
+
+/// Whether the sixth digit of `digest` is a position of the password that
+/// no hash found before already filled.
+fn fills_position(digest: &Digest, found: &[Digest]) -> bool {
+    let position = digest.nibble(5);
+    position < 8 && found.iter().all(|hash| hash.nibble(5) != position)
+}
+
+fn find_second_password<P: FnMut(&Progress) + Send>(input: &str, progress: P) -> String {
+    let hashes = find_hashes(input, 5, 8, workers(), fills_position, progress);
+    password(second_slots(&hashes))
+}
+
+/// Decrypts the password like in the movies, on a single terminal line:
+/// characters already found stay put while the others keep flickering.
+struct Reveal {
+    label: &'static str,
+    slots: fn(&[Digest]) -> Slots,
+    rng: StdRng,
+    found: usize,
+    last_frame: Option<Instant>,
+}
+
+impl Reveal {
+    /// Time between two frames while nothing new is found.
+    const FRAME: Duration = Duration::from_millis(40);
+
+    fn new(label: &'static str, slots: fn(&[Digest]) -> Slots, rng: StdRng) -> Self {
+        Self {
+            label,
+            slots,
+            rng,
+            found: 0,
+            last_frame: None,
         }
-    });
-    let password = hashes.iter().fold(['_'; 8], |mut password, hash| {
-        let position = hash.chars().nth(5).unwrap();
-        let char = hash.chars().nth(6).unwrap();
-        let position = format!("{position}").parse::<usize>().unwrap();
-        password[position] = char;
-        password
-    });
-    password.iter().collect::<String>()
+    }
+
+    /// Random digits where the password is still unknown.
+    fn frame(&mut self, found: &[Digest]) -> String {
+        (self.slots)(found)
+            .iter()
+            .map(|slot| slot.unwrap_or_else(|| hex_digit(self.rng.gen_range(0..16))))
+            .collect()
+    }
+
+    fn update(&mut self, progress: &Progress) {
+        let due = self
+            .last_frame
+            .is_none_or(|last| last.elapsed() >= Self::FRAME);
+        if due || progress.found.len() > self.found {
+            self.found = progress.found.len();
+            self.last_frame = Some(Instant::now());
+            let frame = self.frame(progress.found);
+            print!("\r{} = {frame}", self.label);
+            io::stdout().flush().unwrap();
+        }
+    }
+
+    /// Clears the line for the final password.
+    fn finish(&self) {
+        print!("\r\x1b[2K");
+    }
 }
 
 fn main() {
     let input = "ffykfhsq";
-    println!("Part 1 = {}", find_password(input));
-    println!("Part 2 = {}", find_second_password(input));
+    let show_progress = env::args().any(|arg| arg == "--progress");
+    // Animations would only clutter a file or a pipe
+    let cinematic = env::args().any(|arg| arg == "--cinematic") && io::stdout().is_terminal();
+    let report = |progress: &Progress, reveal: &mut Option<Reveal>| {
+        if show_progress {
+            eprint!(
+                "\r{} hashes checked, {} found",
+                progress.checked,
+                progress.found.len()
+            );
+        }
+        if let Some(reveal) = reveal {
+            reveal.update(progress);
+        }
+    };
+    let mut reveal = cinematic.then(|| Reveal::new("Part 1", first_slots, StdRng::from_entropy()));
+    let password = find_password(input, |progress| report(progress, &mut reveal));
+    if show_progress {
+        eprintln!();
+    }
+    reveal.iter().for_each(Reveal::finish);
+    println!("Part 1 = {password}");
+    let mut reveal = cinematic.then(|| Reveal::new("Part 2", second_slots, StdRng::from_entropy()));
+    let password = find_second_password(input, |progress| report(progress, &mut reveal));
+    if show_progress {
+        eprintln!();
+    }
+    reveal.iter().for_each(Reveal::finish);
+    println!("Part 2 = {password}");
 }
 
 #[cfg(test)]
@@ -56,12 +241,65 @@ mod day05_tests {
     #[test]
     fn part1() {
         let input = "abc";
-        assert_eq!(find_password(input), "18f47a30".to_string())
+        assert_eq!(find_password(input, |_| ()), "18f47a30".to_string())
     }
 
     #[test]
     fn part2() {
         let input = "abc";
-        assert_eq!(find_second_password(input), "05ace8e3".to_string())
+        assert_eq!(find_second_password(input, |_| ()), "05ace8e3".to_string())
+    }
+
+    #[test]
+    fn test_slots() {
+        // The first hash of the examples, 000001 5...
+        let found = [Digest::of("abc3231929")];
+        assert_eq!(
+            first_slots(&found),
+            [Some('1'), None, None, None, None, None, None, None]
+        );
+        assert_eq!(
+            second_slots(&found),
+            [None, Some('5'), None, None, None, None, None, None]
+        );
+        assert_eq!(password(second_slots(&found)), "_5______");
+    }
+
+    #[test]
+    fn test_reveal() {
+        let found = [Digest::of("abc3231929")];
+        let mut reveal = Reveal::new("Part 2", second_slots, StdRng::seed_from_u64(5));
+        let frames = (0..20).map(|_| reveal.frame(&found)).collect::<Vec<_>>();
+        assert!(frames.iter().all(|frame| frame.len() == 8
+            && frame.chars().nth(1) == Some('5')
+            && frame.chars().all(|c| c.is_ascii_hexdigit())));
+        // The unknown characters keep changing
+        assert!(frames.windows(2).any(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn test_workers() {
+        // Hashes with only two leading zeros keep this quick
+        let mut sequential = vec![];
+        let mut index = 0;
+        while sequential.len() < 8 {
+            let digest = Digest::of(format!("abc{index}"));
+            if digest.leading_zeros() >= 2 && fills_position(&digest, &sequential) {
+                sequential.push(digest);
+            }
+            index += 1;
+        }
+        [1, 2, 3, 8].into_iter().for_each(|workers| {
+            let mut updates = vec![];
+            let found = find_hashes("abc", 2, 8, workers, fills_position, |progress| {
+                updates.push((progress.checked, progress.found.len()))
+            });
+            assert_eq!(found, sequential);
+            // Progress only ever moves forward, and ends with every hash
+            assert!(updates
+                .windows(2)
+                .all(|w| w[0].0 < w[1].0 && w[0].1 <= w[1].1));
+            assert_eq!(updates.last().unwrap().1, 8);
+        });
     }
 }
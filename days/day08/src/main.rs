@@ -1,6 +1,6 @@
-use std::usize;
+use std::fmt::Display;
 
-use common::read_input;
+use common::{grid::Grid, read_input};
 
 enum Instruction {
     Rect(usize, usize),
@@ -35,82 +35,34 @@ impl From<&str> for Instruction {
 }
 
 struct SmallDisplay {
-    rows: usize,
-    cols: usize,
-    pixels: Vec<char>,
+    pixels: Grid<char>,
 }
 
 impl SmallDisplay {
     fn new(rows: usize, cols: usize) -> Self {
         SmallDisplay {
-            rows,
-            cols,
-            pixels: vec!['.'; rows * cols],
+            pixels: Grid::new(cols, rows, '.'),
         }
     }
 
     fn count_lit_pixels(&self) -> usize {
-        self.pixels.iter().filter(|&c| *c == '#').count()
-    }
-
-    fn rotate_sequence(sequence: Vec<char>, n: usize) -> Vec<char> {
-        if n == 0 {
-            return sequence;
-        }
-        let (last, rest) = sequence.split_last().unwrap();
-        let mut new_sequence = Vec::with_capacity(sequence.len());
-        new_sequence.push(last.clone());
-        new_sequence.extend_from_slice(rest);
-        Self::rotate_sequence(new_sequence, n - 1)
+        self.pixels.iter().filter(|(_, c)| **c == '#').count()
     }
 
     fn apply(&mut self, instruction: Instruction) {
         match instruction {
             Instruction::Rect(cols, rows) => (0..rows).for_each(|r| {
-                (0..cols).for_each(|c| {
-                    let idx = r * self.cols + c;
-                    *self.pixels.get_mut(idx).unwrap() = '#';
-                })
+                (0..cols).for_each(|c| self.pixels[(c, r)] = '#');
             }),
-            Instruction::RotateColumn(col, qty) => {
-                let qty = qty % self.rows;
-                let col_pixels = (0..self.rows)
-                    .map(|r| {
-                        let idx = r * self.cols + col;
-                        self.pixels.get(idx).unwrap().clone()
-                    })
-                    .collect::<Vec<_>>();
-                let new_col_pixels = Self::rotate_sequence(col_pixels, qty);
-                new_col_pixels.iter().enumerate().for_each(|(r, char)| {
-                    let idx = r * self.cols + col;
-                    *self.pixels.get_mut(idx).unwrap() = *char;
-                });
-            }
-            Instruction::RotateRow(row, qty) => {
-                let qty = qty % self.cols;
-                let row_pixels = (0..self.cols)
-                    .map(|c| {
-                        let idx = row * self.cols + c;
-                        self.pixels.get(idx).unwrap().clone()
-                    })
-                    .collect::<Vec<_>>();
-                let new_row_pixels = Self::rotate_sequence(row_pixels, qty);
-                new_row_pixels.iter().enumerate().for_each(|(c, char)| {
-                    let idx = row * self.cols + c;
-                    *self.pixels.get_mut(idx).unwrap() = *char;
-                });
-            }
+            Instruction::RotateColumn(col, qty) => self.pixels.rotate_column(col, qty),
+            Instruction::RotateRow(row, qty) => self.pixels.rotate_row(row, qty),
         }
     }
 }
 
-impl ToString for SmallDisplay {
-    fn to_string(&self) -> String {
-        self.pixels
-            .chunks(self.cols)
-            .map(|chunk| chunk.iter().collect::<String>())
-            .collect::<Vec<_>>()
-            .join("\n")
+impl Display for SmallDisplay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.pixels)
     }
 }
 
@@ -124,7 +76,7 @@ fn main() {
         .collect::<Vec<_>>();
     instructions.into_iter().for_each(|i| display.apply(i));
     println!("Part 1 = {}", display.count_lit_pixels());
-    println!("Part 2 = \n{}", display.to_string());
+    println!("Part 2 = \n{}", display);
 }
 
 #[cfg(test)]
@@ -1,387 +1,478 @@
-use std::{
-    collections::{HashMap, HashSet},
-    hash::Hash,
-};
-
-use common::read_input;
-
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
-enum Material {
-    Hydrogen,
-    Lithium,
-    Polonium,
-    Thulium,
-    Promethium,
-    Ruthenium,
-    Cobalt,
-    Elerium,
-    Dilithium,
-}
+use std::{env, rc::Rc, thread, time::Duration};
 
-impl ToString for Material {
-    fn to_string(&self) -> String {
-        match self {
-            Material::Hydrogen => "H".to_string(),
-            Material::Lithium => "Li".to_string(),
-            Material::Polonium => "Po".to_string(),
-            Material::Thulium => "Th".to_string(),
-            Material::Promethium => "Pr".to_string(),
-            Material::Ruthenium => "R".to_string(),
-            Material::Cobalt => "Co".to_string(),
-            Material::Elerium => "El".to_string(),
-            Material::Dilithium => "Di".to_string(),
-        }
-    }
-}
+use common::{read_input, search::astar_by_key};
 
-impl From<&str> for Material {
-    fn from(value: &str) -> Self {
-        match value {
-            "hydrogen" | "hydrogen-compatible" => Self::Hydrogen,
-            "lithium" | "lithium-compatible" => Self::Lithium,
-            "polonium" | "polonium-compatible" => Self::Polonium,
-            "thulium" | "thulium-compatible" => Self::Thulium,
-            "promethium" | "promethium-compatible" => Self::Promethium,
-            "ruthenium" | "ruthenium-compatible" => Self::Ruthenium,
-            "cobalt" | "cobalt-compatible" => Self::Cobalt,
-            "elerium" | "elerium-compatible" => Self::Elerium,
-            "dilithium" | "dilithium-compatible" => Self::Dilithium,
-            m => panic!("Unknown material {m}"),
-        }
-    }
-}
+/// A material, interned by its facility as the index of its name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Material(usize);
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Item {
     Generator(Material),
     Microchip(Material),
 }
 
-impl ToString for Item {
-    fn to_string(&self) -> String {
-        match self {
-            Item::Generator(m) => format!("G{}", m.to_string()),
-            Item::Microchip(m) => format!("M{}", m.to_string()),
-        }
+const ORDINALS: [&str; 20] = [
+    "zeroth",
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+    "eleventh",
+    "twelfth",
+    "thirteenth",
+    "fourteenth",
+    "fifteenth",
+    "sixteenth",
+    "seventeenth",
+    "eighteenth",
+    "nineteenth",
+];
+
+const TENS: [(&str, &str); 8] = [
+    ("twenty", "twentieth"),
+    ("thirty", "thirtieth"),
+    ("forty", "fortieth"),
+    ("fifty", "fiftieth"),
+    ("sixty", "sixtieth"),
+    ("seventy", "seventieth"),
+    ("eighty", "eightieth"),
+    ("ninety", "ninetieth"),
+];
+
+/// Parses an ordinal below one hundred, either spelled out ("third",
+/// "twenty-first") or as digits with a suffix ("3rd", "21st").
+fn parse_ordinal(value: &str) -> Option<usize> {
+    if let Some(n) = ["st", "nd", "rd", "th"]
+        .iter()
+        .find_map(|suffix| value.strip_suffix(suffix)?.parse().ok())
+    {
+        return Some(n);
     }
-}
-
-impl From<&str> for Item {
-    fn from(value: &str) -> Self {
-        let (material, item) = value
-            .trim()
-            .strip_prefix("a ")
-            .unwrap()
-            .split_once(' ')
-            .unwrap();
-        match item {
-            "generator" => Self::Generator(material.into()),
-            "microchip" => Self::Microchip(material.into()),
-            i => panic!("Unknown item {i}"),
-        }
+    if let Some(n) = ORDINALS.iter().position(|o| *o == value) {
+        return Some(n);
     }
+    TENS.iter()
+        .enumerate()
+        .find_map(|(n, (cardinal, ordinal))| {
+            let tens = 10 * (n + 2);
+            if value == *ordinal {
+                return Some(tens);
+            }
+            let unit = value.strip_prefix(cardinal)?.strip_prefix('-')?;
+            ORDINALS[1..10]
+                .iter()
+                .position(|o| *o == unit)
+                .map(|u| tens + u + 1)
+        })
 }
 
-#[derive(Debug, Clone)]
-struct Floor {
-    microchips: HashSet<Item>,
-    generators: HashSet<Item>,
+/// One trip of the elevator, with the one or two items it carries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Move {
+    items: Vec<Item>,
+    from: usize,
+    to: usize,
 }
 
-impl PartialEq for Floor {
-    fn eq(&self, other: &Self) -> bool {
-        self.generators.len() == other.generators.len()
-            && self.microchips.len() == other.microchips.len()
-    }
+/// Floors of the generator and of the microchip of one material, when the
+/// facility has them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Pair {
+    generator: Option<usize>,
+    microchip: Option<usize>,
 }
 
-impl Hash for Floor {
-    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-        self.microchips.len().hash(state);
-        self.generators.len().hash(state);
-    }
-}
-
-impl ToString for Floor {
-    fn to_string(&self) -> String {
-        let mut string = self.generators.iter().fold(String::new(), |mut s, g| {
-            s.push_str(&g.to_string());
-            s
-        });
-        string = self.microchips.iter().fold(string, |mut s, m| {
-            s.push_str(&m.to_string());
-            s
-        });
-        string
-    }
+/// A facility up to a renaming of its materials: swapping the floors of two
+/// whole pairs gives an equivalent state, which is solved in the same number
+/// of steps.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CanonicalState {
+    elevator_level: usize,
+    pairs: Vec<Pair>,
 }
 
-impl From<Vec<Item>> for Floor {
-    fn from(value: Vec<Item>) -> Self {
-        let (microchips, generators) = value.iter().cloned().fold(
-            (HashSet::new(), HashSet::new()),
-            |(mut microchips, mut generators), item| {
-                match item {
-                    g @ Item::Generator(_) => generators.insert(g),
-                    m @ Item::Microchip(_) => microchips.insert(m),
-                };
-                (microchips, generators)
-            },
-        );
-        Self {
-            microchips,
-            generators,
-        }
-    }
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Facility {
+    num_floors: usize,
+    elevator_level: usize,
+    materials: Rc<Vec<String>>,
+    // Floor of every item, the generator of material `m` at `2 * m` and its
+    // microchip at `2 * m + 1`
+    positions: Vec<Option<usize>>,
 }
 
-impl Floor {
-    fn add_items(&mut self, items: Vec<Item>) {
-        items.iter().cloned().for_each(|item| {
-            match item {
-                g @ Item::Generator(_) => self.generators.insert(g),
-                m @ Item::Microchip(_) => self.microchips.insert(m),
+impl From<&str> for Facility {
+    fn from(value: &str) -> Self {
+        let mut facility = Self {
+            num_floors: 0,
+            elevator_level: 0,
+            materials: Rc::default(),
+            positions: vec![],
+        };
+        value.lines().for_each(|line| {
+            let line = line.trim().strip_suffix(".").unwrap();
+            let (_, line) = line.split_once(' ').unwrap();
+            let (floor_num, line) = line.split_once(' ').unwrap();
+            let floor_num = match parse_ordinal(floor_num) {
+                Some(n) if n > 0 => n - 1,
+                _ => panic!("Unknown floor {floor_num}"),
             };
+            facility.num_floors = facility.num_floors.max(floor_num + 1);
+            let (_, line) = line.split_once(' ').unwrap();
+            let (_, line) = line.split_once(' ').unwrap();
+            // Items are separated by ", ", " and " or ", and ", which material
+            // names such as "andium" must not be mistaken for
+            line.split(", ")
+                .flat_map(|item| item.strip_prefix("and ").unwrap_or(item).split(" and "))
+                .filter(|item| *item != "nothing relevant")
+                .for_each(|item| {
+                    let item = facility.parse_item(item);
+                    facility.add_item(item, floor_num)
+                });
         });
+        facility
     }
+}
 
-    fn remove_items(&mut self, items: &Vec<Item>) {
-        items.iter().for_each(|item| {
-            match item {
-                g @ Item::Generator(_) => self.generators.remove(g),
-                m @ Item::Microchip(_) => self.microchips.remove(m),
-            };
-        });
+impl Facility {
+    /// None when the input had no floors at all.
+    fn top_floor(&self) -> Option<usize> {
+        self.num_floors.checked_sub(1)
     }
 
-    fn all_items(&self) -> Vec<Item> {
-        let mut items = self
-            .microchips
-            .iter()
-            .cloned()
-            .fold(vec![], |mut items, item| {
-                items.push(item);
-                items
-            });
-        items = self
-            .generators
+    fn is_final(&self) -> bool {
+        self.positions
             .iter()
-            .cloned()
-            .fold(items, |mut items, item| {
-                items.push(item);
-                items
-            });
-        items
+            .flatten()
+            .all(|floor| Some(*floor) == self.top_floor())
     }
 
-    fn is_valid(&self) -> bool {
-        if self.microchips.is_empty() || self.generators.is_empty() {
-            return true;
+    /// Returns the material with the given name, registering it the first
+    /// time it shows up.
+    fn material(&mut self, name: &str) -> Material {
+        match self.materials.iter().position(|m| m == name) {
+            Some(index) => Material(index),
+            None => {
+                Rc::make_mut(&mut self.materials).push(name.to_owned());
+                self.positions.extend([None, None]);
+                Material(self.materials.len() - 1)
+            }
         }
-        self.microchips.iter().all(|m| match m {
-            Item::Microchip(m) => self.generators.contains(&Item::Generator(m.clone())),
-            i => panic!("Wrong item in microchips {:?}", i),
-        })
     }
-}
 
-#[derive(PartialEq, Hash, Clone, Debug)]
-struct FacilityStatus {
-    elevator_level: usize,
-    floors: Vec<String>,
-}
+    fn parse_item(&mut self, value: &str) -> Item {
+        let value = value.trim();
+        let value = value
+            .strip_prefix("a ")
+            .or_else(|| value.strip_prefix("an "))
+            .unwrap_or_else(|| panic!("Unknown item {value}"));
+        if let Some(material) = value.strip_suffix(" generator") {
+            Item::Generator(self.material(material))
+        } else if let Some(material) = value.strip_suffix("-compatible microchip") {
+            Item::Microchip(self.material(material))
+        } else {
+            panic!("Unknown item {value}")
+        }
+    }
 
-impl From<&Facility> for FacilityStatus {
-    fn from(value: &Facility) -> Self {
-        let elevator_level = value.elevator_level;
-        let mut floors = value
-            .floors
-            .iter()
-            .map(|(n, f)| format!("{n}{}", f.to_string()))
-            .collect::<Vec<_>>();
-        floors.sort();
-        Self {
-            elevator_level,
-            floors,
+    fn add_item(&mut self, item: Item, floor: usize) {
+        match item {
+            Item::Generator(Material(m)) => self.positions[2 * m] = Some(floor),
+            Item::Microchip(Material(m)) => self.positions[2 * m + 1] = Some(floor),
         }
     }
-}
 
-#[derive(Debug, Clone, PartialEq)]
-struct Facility {
-    num_floors: usize,
-    floors: HashMap<usize, Floor>,
-    elevator_level: usize,
-}
+    fn pairs(&self) -> impl Iterator<Item = Pair> + '_ {
+        self.positions.chunks(2).map(|pair| Pair {
+            generator: pair[0],
+            microchip: pair[1],
+        })
+    }
 
-impl From<&str> for Facility {
-    fn from(value: &str) -> Self {
-        let num_floors = value.lines().count();
-        let floors = value.lines().fold(HashMap::new(), |mut floors, line| {
-            let line = line.replace(", and", ",");
-            let line = line.replace(" and", ",");
-            let line = line.trim().strip_suffix(".").unwrap();
-            let (_, line) = line.split_once(' ').unwrap();
-            let (floor_num, line) = line.split_once(' ').unwrap();
-            let floor_num = match floor_num {
-                "first" => 0,
-                "second" => 1,
-                "third" => 2,
-                "fourth" => 3,
-                f => panic!("Unknown floor {f}"),
-            };
-            let (_, line) = line.split_once(' ').unwrap();
-            let (_, line) = line.split_once(' ').unwrap();
-            let items: Vec<Item> = line
-                .split(',')
-                .filter_map(|item| match item {
-                    "nothing relevant" => None,
-                    i => Some(i.into()),
-                })
-                .collect();
-            floors.entry(floor_num).or_insert(items.into());
-            floors
-        });
-        Self {
-            num_floors,
-            floors,
-            elevator_level: 0,
+    fn canonical_state(&self) -> CanonicalState {
+        let mut pairs = self.pairs().collect::<Vec<_>>();
+        pairs.sort();
+        CanonicalState {
+            elevator_level: self.elevator_level,
+            pairs,
         }
     }
-}
 
-fn all_combinations_of_size(v: &Floor, n: usize) -> Vec<Vec<Item>> {
-    fn combine_rec(
-        start: usize,
-        items: &[Item],
-        n: usize,
-        current: &mut Vec<Item>,
-        result: &mut Vec<Vec<Item>>,
-    ) {
-        if current.len() == n {
-            current.sort();
-            result.push(current.clone());
-            return;
-        }
-        (start..items.len()).for_each(|i| {
-            current.push(items[i].clone());
-            combine_rec(i + 1, items, n, current, result);
-            current.pop();
+    /// A microchip gets fried when it shares a floor with another generator
+    /// while its own generator is elsewhere.
+    fn is_valid(&self) -> bool {
+        self.pairs().all(|pair| match pair.microchip {
+            Some(floor) if pair.generator != Some(floor) => {
+                self.pairs().all(|other| other.generator != Some(floor))
+            }
+            _ => true,
         })
     }
 
-    let mut result = vec![];
-    let mut current = vec![];
-    let items = v.all_items();
-    combine_rec(0, &items, n, &mut current, &mut result);
-    result.sort();
-    result.dedup();
-    result
-}
-
-impl Facility {
-    fn is_final(&self) -> bool {
-        (0..self.num_floors - 1)
-            .map(|n| self.floors.get(&n).unwrap())
-            .all(|f| f.microchips.is_empty() && f.generators.is_empty())
+    fn item_at(index: usize) -> Item {
+        match index % 2 {
+            0 => Item::Generator(Material(index / 2)),
+            _ => Item::Microchip(Material(index / 2)),
+        }
     }
 
-    fn add_item(&mut self, item: Item, floor: usize) {
-        self.floors.get_mut(&floor).unwrap().add_items(vec![item]);
+    fn next_moves(&self) -> Vec<(Move, Self)> {
+        let here = (0..self.positions.len())
+            .filter(|i| self.positions[*i] == Some(self.elevator_level))
+            .collect::<Vec<_>>();
+        let loads = here.iter().enumerate().flat_map(|(n, first)| {
+            std::iter::once(vec![*first])
+                .chain(here[n + 1..].iter().map(|second| vec![*first, *second]))
+        });
+        let levels = [
+            self.elevator_level.checked_sub(1),
+            Some(self.elevator_level + 1).filter(|level| *level < self.num_floors),
+        ];
+        loads
+            .flat_map(|load| {
+                levels.iter().flatten().filter_map(move |level| {
+                    let mut next = self.clone();
+                    next.elevator_level = *level;
+                    load.iter().for_each(|i| next.positions[*i] = Some(*level));
+                    let step = Move {
+                        items: load.iter().map(|i| Facility::item_at(*i)).collect(),
+                        from: self.elevator_level,
+                        to: *level,
+                    };
+                    next.is_valid().then_some((step, next))
+                })
+            })
+            .collect()
     }
 
     fn next_states(&self) -> Vec<Self> {
-        let mut combinations =
-            all_combinations_of_size(self.floors.get(&self.elevator_level).unwrap(), 2);
-        combinations.extend(all_combinations_of_size(
-            self.floors.get(&self.elevator_level).unwrap(),
-            1,
-        ));
-        let mut next_states = vec![];
-        combinations.iter().for_each(|comb| {
-            let mut current_floor = self.floors.get(&self.elevator_level).unwrap().clone();
-            current_floor.remove_items(comb);
-            if self.elevator_level < self.num_floors - 1 {
-                let mut next_floor = self.floors.get(&(self.elevator_level + 1)).unwrap().clone();
-                next_floor.add_items(comb.clone());
-                if next_floor.is_valid() {
-                    let mut floors = self.floors.clone();
-                    *floors.get_mut(&self.elevator_level).unwrap() = current_floor.clone();
-                    *floors.get_mut(&(self.elevator_level + 1)).unwrap() = next_floor.clone();
-                    next_states.push(Facility {
-                        num_floors: self.num_floors,
-                        floors,
-                        elevator_level: self.elevator_level + 1,
-                    });
-                }
-            }
-            if self.elevator_level > 0 && self.elevator_level <= self.num_floors - 1 {
-                let mut next_floor = self.floors.get(&(self.elevator_level - 1)).unwrap().clone();
-                next_floor.add_items(comb.clone());
-                if next_floor.is_valid() {
-                    let mut floors = self.floors.clone();
-                    *floors.get_mut(&self.elevator_level).unwrap() = current_floor.clone();
-                    *floors.get_mut(&(self.elevator_level - 1)).unwrap() = next_floor.clone();
-                    next_states.push(Facility {
-                        num_floors: self.num_floors,
-                        floors,
-                        elevator_level: self.elevator_level - 1,
-                    });
+        self.next_moves()
+            .into_iter()
+            .map(|(_, next)| next)
+            .collect()
+    }
+
+    /// Every move crosses the boundary between two adjacent floors. The `n`
+    /// items below a boundary need at least `n - 1` trips up carrying two of
+    /// them, each but the last followed by a trip down carrying one back, so
+    /// counting the crossings needed at every boundary never overestimates.
+    fn min_remaining_steps(&self) -> usize {
+        (0..self.top_floor().unwrap_or(0))
+            .map(|boundary| {
+                let below = self
+                    .positions
+                    .iter()
+                    .flatten()
+                    .filter(|floor| **floor <= boundary)
+                    .count();
+                match below {
+                    0 => 0,
+                    n if self.elevator_level > boundary => 2 * n,
+                    1 => 1,
+                    n => 2 * n - 3,
                 }
-            }
-        });
-        next_states
+            })
+            .sum()
     }
 
-    fn score(&self) -> usize {
-        self.floors
+    /// Finds the shortest sequence of elevator trips bringing every item to
+    /// the top floor.
+    fn solve(&self) -> Option<Vec<Move>> {
+        let path = astar_by_key(
+            self.clone(),
+            Facility::canonical_state,
+            |facility| {
+                facility
+                    .next_states()
+                    .into_iter()
+                    .map(|next| (next, 1))
+                    .collect::<Vec<_>>()
+            },
+            Facility::min_remaining_steps,
+            Facility::is_final,
+        )?;
+        // The search only remembers canonical states, so replay it picking
+        // at every step a move that lands on the next one
+        let mut current = self.clone();
+        let moves = path.states[1..]
             .iter()
-            .map(|(n, floor)| floor.all_items().len() * 16usize.pow(*n as u32))
-            .sum()
+            .map(|state| {
+                let (step, next) = current
+                    .next_moves()
+                    .into_iter()
+                    .find(|(_, next)| next.canonical_state() == *state)
+                    .unwrap();
+                current = next;
+                step
+            })
+            .collect();
+        Some(moves)
     }
 
-    fn count_steps(&self) -> usize {
-        let mut visited = vec![];
-        let mut next_states = vec![(self.clone(), 0)];
-        let mut max = 0;
+    fn count_steps(&self) -> Option<usize> {
+        self.solve().map(|moves| moves.len())
+    }
 
-        while !next_states.is_empty() {
-            let (current_state, count) = next_states.remove(0);
-            if current_state.is_final() {
-                return count;
-            }
-            if current_state.score() > max {
-                max = current_state.score();
-            }
-            let mut neighbors = current_state.next_states();
-            neighbors.sort_by(|s1, s2| s2.score().cmp(&s1.score()));
-            neighbors
-                .iter()
-                .filter(|n| max - n.score().clamp(0, max) < 8192)
-                .for_each(|neighbor| {
-                    if !visited.contains(neighbor) {
-                        next_states.push((neighbor.clone(), count + 1));
-                        visited.push(neighbor.clone());
-                    }
-                })
+    fn apply(&self, step: &Move) -> Self {
+        let mut next = self.clone();
+        next.elevator_level = step.to;
+        step.items
+            .iter()
+            .for_each(|item| next.add_item(*item, step.to));
+        next
+    }
+
+    /// Symbols of the materials: their capitalized prefixes, all of the same
+    /// length and long enough to tell them apart.
+    fn symbols(&self) -> Vec<String> {
+        let longest = self.materials.iter().map(|m| m.len()).max().unwrap_or(0);
+        let length = (1..longest)
+            .find(|length| {
+                let mut prefixes = self
+                    .materials
+                    .iter()
+                    .map(|m| m.chars().take(*length).collect::<String>())
+                    .collect::<Vec<_>>();
+                prefixes.sort();
+                prefixes.dedup();
+                prefixes.len() == self.materials.len()
+            })
+            .unwrap_or(longest);
+        self.materials
+            .iter()
+            .map(|m| {
+                let prefix = m.chars().take(length).collect::<String>();
+                let mut chars = prefix.chars();
+                chars
+                    .next()
+                    .map(|first| first.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    fn item_symbol(&self, symbols: &[String], item: Item) -> String {
+        match item {
+            Item::Generator(Material(m)) => format!("{}G", symbols[m]),
+            Item::Microchip(Material(m)) => format!("{}M", symbols[m]),
         }
-        0
+    }
+
+    fn describe(&self, step: &Move) -> String {
+        let symbols = self.symbols();
+        let items = step
+            .items
+            .iter()
+            .map(|item| self.item_symbol(&symbols, *item))
+            .collect::<Vec<_>>()
+            .join(" and ");
+        format!("{items} from F{} to F{}", step.from + 1, step.to + 1)
+    }
+
+    /// Draws the facility the way the puzzle does, top floor first, one
+    /// column for the elevator and one for every item.
+    fn render(&self) -> String {
+        let symbols = self.symbols();
+        let width = symbols.first().map_or(1, |s| s.len() + 1);
+        (0..self.num_floors)
+            .rev()
+            .map(|floor| {
+                let elevator = if self.elevator_level == floor {
+                    "E"
+                } else {
+                    "."
+                };
+                let items = self
+                    .positions
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, position)| position.is_some())
+                    .map(|(i, position)| {
+                        if *position == Some(floor) {
+                            self.item_symbol(&symbols, Facility::item_at(i))
+                        } else {
+                            ".".to_owned()
+                        }
+                    })
+                    .map(|cell| format!("{cell:width$}"));
+                let row = std::iter::once(format!("F{}", floor + 1))
+                    .chain(std::iter::once(format!("{elevator:width$}")))
+                    .chain(items)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                row.trim_end().to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Lists every state of a solution, each one preceded by the move that
+    /// leads to it.
+    fn render_solution(&self, moves: &[Move]) -> String {
+        let mut current = self.clone();
+        let mut frames = vec![current.render()];
+        moves.iter().enumerate().for_each(|(n, step)| {
+            let description = current.describe(step);
+            current = current.apply(step);
+            frames.push(format!(
+                "Step {}: {description}\n{}",
+                n + 1,
+                current.render()
+            ));
+        });
+        frames.join("\n\n")
     }
 }
 
+/// Plays a solution back in the terminal, redrawing the facility in place.
+fn animate(facility: &Facility, moves: &[Move], delay: Duration) {
+    let mut current = facility.clone();
+    println!("\x1b[2J\x1b[H{}", current.render());
+    moves.iter().enumerate().for_each(|(n, step)| {
+        thread::sleep(delay);
+        let description = current.describe(step);
+        current = current.apply(step);
+        println!(
+            "\x1b[2J\x1b[HStep {}/{}: {description}\n{}",
+            n + 1,
+            moves.len(),
+            current.render()
+        );
+    });
+}
+
 fn main() {
     let input = read_input("day11.txt");
     let mut facility = Facility::from(input.as_str());
-    println!("Part 1 = {}", facility.count_steps());
-    facility.add_item(Item::Generator(Material::Elerium), 0);
-    facility.add_item(Item::Microchip(Material::Elerium), 0);
-    facility.add_item(Item::Generator(Material::Dilithium), 0);
-    facility.add_item(Item::Microchip(Material::Dilithium), 0);
-    println!("Part 2 = {}", facility.count_steps());
+    println!("Part 1 = {}", facility.count_steps().unwrap());
+    ["elerium", "dilithium"].into_iter().for_each(|name| {
+        let material = facility.material(name);
+        facility.add_item(Item::Generator(material), 0);
+        facility.add_item(Item::Microchip(material), 0);
+    });
+    println!("Part 2 = {}", facility.count_steps().unwrap());
+    match env::args().nth(1).as_deref() {
+        Some("--list") => println!("{}", facility.render_solution(&facility.solve().unwrap())),
+        Some("--animate") => animate(
+            &facility,
+            &facility.solve().unwrap(),
+            Duration::from_millis(250),
+        ),
+        _ => (),
+    }
 }
 
 #[cfg(test)]
 mod day11_tests {
+    use parameterized::parameterized;
+
     use super::*;
 
     #[test]
@@ -391,7 +482,7 @@ The second floor contains nothing relevant.
 The third floor contains nothing relevant.
 The fourth floor contains nothing relevant."#;
         let facility = Facility::from(input);
-        assert_eq!(facility.count_steps(), 3);
+        assert_eq!(facility.count_steps(), Some(3));
     }
 
     #[test]
@@ -401,7 +492,7 @@ The second floor contains nothing relevant.
 The third floor contains nothing relevant.
 The fourth floor contains nothing relevant."#;
         let facility = Facility::from(input);
-        assert_eq!(facility.count_steps(), 3);
+        assert_eq!(facility.count_steps(), Some(3));
     }
 
     #[test]
@@ -411,7 +502,7 @@ The second floor contains a hydrogen-compatible microchip.
 The third floor contains nothing relevant.
 The fourth floor contains nothing relevant."#;
         let facility = Facility::from(input);
-        assert_eq!(facility.count_steps(), 3);
+        assert_eq!(facility.count_steps(), Some(3));
     }
 
     #[test]
@@ -421,6 +512,125 @@ The fourth floor contains nothing relevant."#;
     The third floor contains a lithium generator.
     The fourth floor contains nothing relevant."#;
         let facility = Facility::from(input);
-        assert_eq!(facility.count_steps(), 11);
+        assert_eq!(facility.count_steps(), Some(11));
+    }
+
+    #[test]
+    fn canonical_state() {
+        let input = r#"The first floor contains nothing relevant.
+The second floor contains nothing relevant.
+The third floor contains nothing relevant.
+The fourth floor contains nothing relevant."#;
+        let mut facility = Facility::from(input);
+        let mut swapped = facility.clone();
+        let hydrogen = facility.material("hydrogen");
+        let lithium = facility.material("lithium");
+        facility.add_item(Item::Generator(hydrogen), 0);
+        facility.add_item(Item::Microchip(hydrogen), 1);
+        facility.add_item(Item::Generator(lithium), 2);
+        facility.add_item(Item::Microchip(lithium), 2);
+        let lithium = swapped.material("lithium");
+        let hydrogen = swapped.material("hydrogen");
+        swapped.add_item(Item::Generator(lithium), 2);
+        swapped.add_item(Item::Microchip(lithium), 2);
+        swapped.add_item(Item::Generator(hydrogen), 0);
+        swapped.add_item(Item::Microchip(hydrogen), 1);
+        assert_ne!(facility.positions, swapped.positions);
+        assert_eq!(facility.canonical_state(), swapped.canonical_state());
+    }
+
+    #[test]
+    fn agrees_with_plain_bfs() {
+        let input = r#"The first floor contains a hydrogen generator, a hydrogen-compatible microchip and a lithium-compatible microchip.
+The second floor contains a lithium generator and a polonium generator.
+The third floor contains a polonium-compatible microchip."#;
+        let facility = Facility::from(input);
+        let expected =
+            common::search::bfs(facility.clone(), Facility::next_states, Facility::is_final)
+                .unwrap()
+                .cost;
+        assert_eq!(facility.count_steps(), Some(expected));
+    }
+
+    #[parameterized(
+        input = { "first", "fourth", "twelfth", "twentieth", "twenty-first", "ninety-ninth", "3rd", "42nd", "fifty", "twenty-", "floor" },
+        expected = { Some(1), Some(4), Some(12), Some(20), Some(21), Some(99), Some(3), Some(42), None, None, None }
+    )]
+    fn test_parse_ordinal(input: &str, expected: Option<usize>) {
+        assert_eq!(parse_ordinal(input), expected);
+    }
+
+    #[test]
+    fn custom_materials_and_floors() {
+        let input = r#"The first floor contains an americium generator, an americium-compatible microchip and a curium-compatible microchip.
+The second floor contains a curium generator.
+The third floor contains nothing relevant.
+The fourth floor contains nothing relevant.
+The fifth floor contains nothing relevant."#;
+        let facility = Facility::from(input);
+        assert_eq!(facility.num_floors, 5);
+        assert_eq!(*facility.materials, vec!["americium", "curium"]);
+        assert_eq!(facility.positions, vec![Some(0), Some(0), Some(1), Some(0)]);
+        let expected =
+            common::search::bfs(facility.clone(), Facility::next_states, Facility::is_final);
+        assert_eq!(expected.map(|path| path.cost), Some(18));
+        assert_eq!(facility.count_steps(), Some(18));
+    }
+
+    #[test]
+    fn no_floors() {
+        let facility = Facility::from("");
+        assert_eq!(facility.num_floors, 0);
+        assert!(facility.is_final());
+        assert_eq!(facility.min_remaining_steps(), 0);
+        assert_eq!(facility.count_steps(), Some(0));
+    }
+
+    #[test]
+    fn materials_named_and() {
+        let input = r#"The first floor contains an andium generator, an andium-compatible microchip, and a sandium generator.
+The second floor contains a sandium-compatible microchip and an andrium generator and an andrium-compatible microchip.
+The third floor contains nothing relevant."#;
+        let facility = Facility::from(input);
+        assert_eq!(*facility.materials, vec!["andium", "sandium", "andrium"]);
+        assert_eq!(
+            facility.positions,
+            vec![Some(0), Some(0), Some(0), Some(1), Some(1), Some(1)]
+        );
+    }
+
+    #[test]
+    fn solution_replay() {
+        let input = r#"The first floor contains a hydrogen-compatible microchip and a lithium-compatible microchip.
+The second floor contains a hydrogen generator.
+The third floor contains a lithium generator.
+The fourth floor contains nothing relevant."#;
+        let facility = Facility::from(input);
+        assert_eq!(
+            facility.render(),
+            r#"F4 .  .  .  .  .
+F3 .  .  .  LG .
+F2 .  HG .  .  .
+F1 E  .  HM .  LM"#
+        );
+        let moves = facility.solve().unwrap();
+        assert_eq!(moves.len(), 11);
+        assert_eq!(
+            moves[0],
+            Move {
+                items: vec![Item::Microchip(Material(0))],
+                from: 0,
+                to: 1
+            }
+        );
+        let last = moves.iter().fold(facility.clone(), |current, step| {
+            assert!(current.next_moves().iter().any(|(m, _)| m == step));
+            current.apply(step)
+        });
+        assert!(last.is_final());
+        let listing = facility.render_solution(&moves);
+        assert!(listing.starts_with(&facility.render()));
+        assert!(listing.contains("Step 1: HM from F1 to F2\nF4 .  .  .  .  .\nF3 .  .  .  LG .\nF2 E  HG HM .  .\nF1 .  .  .  .  LM"));
+        assert!(listing.ends_with(&last.render()));
     }
 }
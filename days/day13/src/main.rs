@@ -1,4 +1,15 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fmt::Display,
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use common::{
+    grid::{Grid, LazyGrid},
+    search::{astar, bfs_distances},
+};
 
 #[derive(Clone)]
 enum Cell {
@@ -6,11 +17,11 @@ enum Cell {
     Wall,
 }
 
-impl ToString for Cell {
-    fn to_string(&self) -> String {
+impl Display for Cell {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Cell::Floor => ".".to_owned(),
-            Cell::Wall => "#".to_owned(),
+            Cell::Floor => write!(f, "."),
+            Cell::Wall => write!(f, "#"),
         }
     }
 }
@@ -24,35 +35,141 @@ impl Cell {
     }
 }
 
+/// The part of the maze to draw: `width` x `height` cells from `origin`.
+#[derive(Debug, Clone, Copy)]
+struct Viewport {
+    origin: (usize, usize),
+    width: usize,
+    height: usize,
+}
+
+/// Extra information drawn on top of the maze, each overlay over the
+/// previous ones.
+enum Overlay {
+    Path(Vec<(usize, usize)>),
+    Reachable(HashSet<(usize, usize)>),
+    Heatmap(HashMap<(usize, usize), usize>),
+}
+
+/// What ends up drawn on a cell.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mark {
+    Wall,
+    Floor,
+    Reachable,
+    // Distance scaled to 0..=9 relative to the farthest cell of the heatmap
+    Heat(usize),
+    Path,
+}
+
+impl Mark {
+    fn symbol(&self) -> char {
+        match self {
+            Mark::Wall => '#',
+            Mark::Floor => '.',
+            Mark::Reachable => 'o',
+            Mark::Heat(level) => char::from_digit(*level as u32, 10).unwrap(),
+            Mark::Path => 'O',
+        }
+    }
+
+    fn color(&self) -> [u8; 3] {
+        match self {
+            Mark::Wall => [48, 48, 64],
+            Mark::Floor => [240, 240, 240],
+            Mark::Reachable => [150, 220, 150],
+            Mark::Heat(level) => {
+                let level = *level as u8;
+                [50 + 20 * level, 60, 230 - 20 * level]
+            }
+            Mark::Path => [250, 160, 20],
+        }
+    }
+}
+
 struct Maze {
-    grid: HashMap<(usize, usize), Cell>,
-    designer_number: usize,
+    grid: LazyGrid<Cell>,
 }
 
 impl Maze {
     fn new(designer_number: usize) -> Self {
         Self {
-            grid: HashMap::new(),
-            designer_number,
+            grid: LazyGrid::new(move |(x, y)| {
+                let value = x * x + 3 * x + 2 * x * y + y + y * y + designer_number;
+                if value.count_ones().is_multiple_of(2) {
+                    Cell::Floor
+                } else {
+                    Cell::Wall
+                }
+            }),
         }
     }
 
-    fn cell_at(&mut self, (x, y): (usize, usize)) -> Cell {
-        if let Some(c) = self.grid.get(&(x, y)) {
-            c.clone()
-        } else {
-            let mut value = x * x + 3 * x + 2 * x * y + y + y * y;
-            value += self.designer_number;
-            let binary = format!("{:b}", value);
-            let count_1 = binary.chars().filter(|c| *c == '1').count();
-            if count_1 % 2 == 0 {
-                self.grid.insert((x, y), Cell::Floor);
-                self.grid.get(&(x, y)).unwrap().clone()
-            } else {
-                self.grid.insert((x, y), Cell::Wall);
-                self.grid.get(&(x, y)).unwrap().clone()
+    fn cell_at(&mut self, position: (usize, usize)) -> Cell {
+        self.grid.get(position).clone()
+    }
+
+    fn open_neighbors(&mut self, position: (usize, usize)) -> Vec<(usize, usize)> {
+        LazyGrid::<Cell>::neighbors4(position)
+            .filter(|&c| self.cell_at(c).is_valid())
+            .collect()
+    }
+
+    /// Number of steps to every cell reachable from `source` in at most
+    /// `max_steps` steps.
+    fn distances_from(
+        &mut self,
+        source: (usize, usize),
+        max_steps: usize,
+    ) -> HashMap<(usize, usize), usize> {
+        bfs_distances(source, max_steps, |&current| self.open_neighbors(current))
+    }
+
+    fn reachable_within(&mut self, source: (usize, usize), max_steps: usize) -> usize {
+        self.distances_from(source, max_steps).len()
+    }
+
+    /// Labels the open cells of the `width` x `height` window at the origin
+    /// by connected component, numbered in reading order. Cells only count
+    /// as connected through paths that stay inside the window.
+    fn components(&mut self, width: usize, height: usize) -> Grid<Option<usize>> {
+        let window = self.grid.window((0, 0), width, height);
+        let mut labels = Grid::new(width, height, None);
+        let mut next_label = 0;
+        window.positions().for_each(|position| {
+            if labels[position].is_some() || !window[position].is_valid() {
+                return;
             }
-        }
+            let component = bfs_distances(position, usize::MAX, |&current| {
+                window
+                    .neighbors4(current)
+                    .filter(|n| window[*n].is_valid())
+                    .collect::<Vec<_>>()
+            });
+            component
+                .keys()
+                .for_each(|cell| labels[*cell] = Some(next_label));
+            next_label += 1;
+        });
+        labels
+    }
+
+    /// The open cell of the window closest to `source` that cannot be
+    /// reached from it without leaving the window, if any. Ties are broken in
+    /// reading order.
+    fn nearest_unreachable(
+        &mut self,
+        source: (usize, usize),
+        width: usize,
+        height: usize,
+    ) -> Option<(usize, usize)> {
+        let labels = self.components(width, height);
+        let source_label = labels.get(source).copied().flatten();
+        labels
+            .iter()
+            .filter(|(_, label)| label.is_some() && **label != source_label)
+            .map(|(position, _)| position)
+            .min_by_key(|&(x, y)| (distance(source, (x, y)), y, x))
     }
 
     fn steps_to(
@@ -62,135 +179,132 @@ impl Maze {
         target_x: usize,
         target_y: usize,
     ) -> Result<Vec<(usize, usize)>, ()> {
-        let mut next_steps: HashSet<(usize, usize)> = HashSet::new();
-        let mut visited: HashSet<(usize, usize)> = HashSet::new();
-        next_steps.insert((source_x, source_y));
-        let mut path: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
-        let mut g_score: HashMap<(usize, usize), i32> = HashMap::new();
-        g_score.insert((source_x, source_y), 0);
-        let mut f_score: HashMap<(usize, usize), usize> = HashMap::new();
-        f_score.insert(
+        let target = (target_x, target_y);
+        let path = astar(
             (source_x, source_y),
-            distance((source_x, source_y), (target_x, target_y)),
-        );
-        while !next_steps.is_empty() {
-            let current = next_steps
-                .iter()
-                .cloned()
-                .min_by_key(|&p| f_score.get(&p).unwrap())
-                .unwrap();
-            if current == (target_x, target_y) {
-                return Ok(rebuild_path((source_x, source_y), &path, current, vec![]));
+            |&current| {
+                self.open_neighbors(current)
+                    .into_iter()
+                    .map(|c| (c, 1))
+                    .collect::<Vec<_>>()
+            },
+            |&current| distance(current, target),
+            |&current| current == target,
+        )
+        .ok_or(())?;
+        Ok(path.states[1..].to_vec())
+    }
+
+    fn marks(&mut self, viewport: &Viewport, overlays: &[Overlay]) -> Grid<Mark> {
+        let mut marks = self
+            .grid
+            .window(viewport.origin, viewport.width, viewport.height)
+            .map(|cell| match cell {
+                Cell::Floor => Mark::Floor,
+                Cell::Wall => Mark::Wall,
+            });
+        let (origin_x, origin_y) = viewport.origin;
+        let mut mark = |(x, y): (usize, usize), value: Mark| {
+            if let Some(cell) = x
+                .checked_sub(origin_x)
+                .zip(y.checked_sub(origin_y))
+                .and_then(|position| marks.get_mut(position))
+            {
+                *cell = value;
             }
-            next_steps.remove(&current);
-            visited.insert(current.clone());
-            neighbors_of(current)
-                .iter()
-                .filter(|&c| self.cell_at(*c).is_valid())
-                .for_each(|neighbor| {
-                    if !visited.contains(neighbor) {
-                        let trial_score = g_score.get(&current).unwrap() + 1;
-                        if trial_score < *g_score.get(&neighbor).unwrap_or(&i32::max_value()) {
-                            path.insert(*neighbor, current.clone());
-                            g_score.insert(*neighbor, trial_score);
-                            f_score.insert(
-                                *neighbor,
-                                trial_score as usize + distance(*neighbor, (target_x, target_y)),
-                            );
-                            next_steps.insert(neighbor.clone());
-                        }
-                    }
-                })
-        }
-        Err(())
+        };
+        overlays.iter().for_each(|overlay| match overlay {
+            Overlay::Path(cells) => cells.iter().for_each(|cell| mark(*cell, Mark::Path)),
+            Overlay::Reachable(cells) => cells.iter().for_each(|cell| mark(*cell, Mark::Reachable)),
+            Overlay::Heatmap(distances) => {
+                let farthest = distances.values().max().copied().unwrap_or(0);
+                distances
+                    .iter()
+                    .for_each(|(cell, d)| mark(*cell, Mark::Heat(d * 9 / farthest.max(1))));
+            }
+        });
+        marks
     }
-}
 
-fn neighbors_of((x, y): (usize, usize)) -> Vec<(usize, usize)> {
-    let mut neighbors = vec![];
-    if x > 0 {
-        neighbors.push((x - 1, y));
+    fn render_ascii(&mut self, viewport: &Viewport, overlays: &[Overlay]) -> String {
+        self.marks(viewport, overlays)
+            .render(|_, mark| mark.symbol())
     }
-    if y > 0 {
-        neighbors.push((x, y - 1));
+
+    /// Writes the viewport as a PNG image, each cell drawn as a `scale` x
+    /// `scale` square.
+    fn render_png<W: Write>(
+        &mut self,
+        viewport: &Viewport,
+        overlays: &[Overlay],
+        scale: usize,
+        writer: W,
+    ) -> Result<(), png::EncodingError> {
+        let marks = self.marks(viewport, overlays);
+        let data = marks
+            .rows()
+            .flat_map(|row| {
+                let line = row
+                    .iter()
+                    .flat_map(|mark| std::iter::repeat_n(mark.color(), scale))
+                    .flatten()
+                    .collect::<Vec<_>>();
+                std::iter::repeat_n(line, scale).flatten()
+            })
+            .collect::<Vec<_>>();
+        let mut encoder = png::Encoder::new(
+            writer,
+            (viewport.width * scale) as u32,
+            (viewport.height * scale) as u32,
+        );
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header()?.write_image_data(&data)
     }
-    neighbors.push((x + 1, y));
-    neighbors.push((x, y + 1));
-    neighbors
 }
 
 fn distance((source_x, source_y): (usize, usize), (target_x, target_y): (usize, usize)) -> usize {
-    let dx = if source_x > target_x {
-        source_x - target_x
-    } else {
-        target_x - source_x
-    };
-    let dy = if source_y > target_y {
-        source_y - target_y
-    } else {
-        target_y - source_y
-    };
-    dx + dy
-}
-
-fn rebuild_path(
-    (source_x, source_y): (usize, usize),
-    path: &HashMap<(usize, usize), (usize, usize)>,
-    current: (usize, usize),
-    mut full_path: Vec<(usize, usize)>,
-) -> Vec<(usize, usize)> {
-    if current == (source_x, source_y) {
-        full_path.reverse();
-        return full_path;
-    }
-    full_path.push(current);
-    rebuild_path(
-        (source_x, source_y),
-        path,
-        *path.get(&current).unwrap(),
-        full_path,
-    )
+    source_x.abs_diff(target_x) + source_y.abs_diff(target_y)
 }
 
 fn main() {
     let mut maze = Maze::new(1364);
     let steps = maze.steps_to(1, 1, 31, 39).unwrap();
     println!("Part 1 = {}", steps.len());
-    // let grid = (0..45)
-    //     .map(|col| {
-    //         let mut row = (0..45)
-    //             .map(|row| {
-    //                 if steps.contains(&(row, col)) {
-    //                     "O".to_owned()
-    //                 } else {
-    //                     maze.cell_at((row, col)).to_string()
-    //                 }
-    //             })
-    //             .collect::<String>();
-    //         row.push('\n');
-    //         row
-    //     })
-    //     .collect::<String>();
-    // println!("{grid}");
-    let targets = (0..51)
-        .flat_map(|col| {
-            (0..51)
-                .filter_map(move |row| {
-                    if distance((1, 1), (row.clone(), col.clone())) < 50 {
-                        Some((row.clone(), col.clone()))
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-    let paths_of_50 = targets
+    println!("Part 2 = {}", maze.reachable_within((1, 1), 50));
+    let components = maze.components(51, 51);
+    let count = components
         .iter()
-        .filter_map(|(tx, ty)| maze.steps_to(1, 1, *tx, *ty).ok())
-        .filter(|steps| steps.len() <= 50)
-        .count();
-    println!("Part 2 = {}", paths_of_50);
+        .filter_map(|(_, label)| *label)
+        .max()
+        .map_or(0, |max| max + 1);
+    println!("Regions in the 51x51 corner = {count}");
+    if let Some(cell) = maze.nearest_unreachable((1, 1), 51, 51) {
+        println!("Nearest unreachable cell = {cell:?}");
+    }
+    let viewport = Viewport {
+        origin: (0, 0),
+        width: 45,
+        height: 45,
+    };
+    let distances = maze.distances_from((1, 1), 50);
+    let args = env::args().collect::<Vec<_>>();
+    match args.get(1).map(String::as_str) {
+        Some("--render") => {
+            let overlays = [
+                Overlay::Reachable(distances.into_keys().collect()),
+                Overlay::Path(steps),
+            ];
+            println!("{}", maze.render_ascii(&viewport, &overlays))
+        }
+        Some("--png") => {
+            let overlays = [Overlay::Heatmap(distances), Overlay::Path(steps)];
+            let file = File::create(args.get(2).map_or("day13.png", String::as_str)).unwrap();
+            maze.render_png(&viewport, &overlays, 8, BufWriter::new(file))
+                .unwrap();
+        }
+        _ => (),
+    }
 }
 
 #[cfg(test)]
@@ -226,21 +340,99 @@ mod day13_tests {
         let mut maze = Maze::new(10);
         let steps = maze.steps_to(1, 1, 7, 4).unwrap();
         assert_eq!(steps.len(), 11);
-        let grid = (0..7)
-            .map(|col| {
-                let mut row = (0..10)
-                    .map(|row| {
-                        if steps.contains(&(row, col)) {
-                            "O".to_owned()
-                        } else {
-                            maze.cell_at((row, col)).to_string()
-                        }
-                    })
-                    .collect::<String>();
-                row.push('\n');
-                row
-            })
-            .collect::<String>();
-        println!("{grid}");
+        let viewport = Viewport {
+            origin: (0, 0),
+            width: 10,
+            height: 7,
+        };
+        assert_eq!(
+            maze.render_ascii(&viewport, &[Overlay::Path(steps)]),
+            r#".#.####.##
+..#..#...#
+#OOO.##...
+###O#.###.
+.##OO#OO#.
+..##OOO.#.
+#...##.###"#
+        );
+    }
+
+    #[test]
+    fn reachable_within() {
+        let mut maze = Maze::new(10);
+        let distances = maze.distances_from((1, 1), 11);
+        assert_eq!(distances[&(1, 1)], 0);
+        assert_eq!(distances[&(7, 4)], 11);
+        let brute_force = (0..12)
+            .flat_map(|y| (0..12).map(move |x| (x, y)))
+            .filter_map(|(x, y)| maze.steps_to(1, 1, x, y).ok())
+            .filter(|steps| steps.len() <= 10)
+            .count();
+        assert_eq!(maze.reachable_within((1, 1), 10), brute_force);
+    }
+
+    #[test]
+    fn components() {
+        let mut maze = Maze::new(10);
+        let labels = maze.components(10, 7);
+        let rendered = labels.render(|_, label| match label {
+            Some(label) => char::from_digit(*label as u32, 10).unwrap(),
+            None => '#',
+        });
+        assert_eq!(
+            rendered,
+            r#"0#1####2##
+00#00#222#
+#0000##222
+###0#3###2
+4##00#00#2
+44##0000#2
+#444##0###"#
+        );
+        assert_eq!(maze.nearest_unreachable((1, 1), 10, 7), Some((2, 0)));
+        assert_eq!(maze.nearest_unreachable((7, 0), 10, 7), Some((4, 1)));
+    }
+
+    #[test]
+    fn overlays() {
+        let mut maze = Maze::new(10);
+        let viewport = Viewport {
+            origin: (2, 1),
+            width: 5,
+            height: 4,
+        };
+        let reachable = maze.distances_from((1, 1), 3);
+        let overlays = [
+            Overlay::Reachable(reachable.keys().copied().collect()),
+            Overlay::Path(vec![(1, 1), (3, 2)]),
+        ];
+        assert_eq!(
+            maze.render_ascii(&viewport, &overlays),
+            r#"#..#.
+oO.##
+#.#.#
+#..#."#
+        );
+        let overlays = [Overlay::Heatmap(reachable)];
+        assert_eq!(
+            maze.render_ascii(&viewport, &overlays),
+            r#"#..#.
+69.##
+#.#.#
+#..#."#
+        );
+
+        let mut png = vec![];
+        maze.render_png(&viewport, &overlays, 3, &mut png).unwrap();
+        let decoder = png::Decoder::new(png.as_slice());
+        let mut reader = decoder.read_info().unwrap();
+        let mut data = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut data).unwrap();
+        assert_eq!((info.width, info.height), (15, 12));
+        // The top left cell is a wall, the second row starts with the two
+        // cells 2 and 3 steps away from the source
+        assert_eq!(data[0..3], Mark::Wall.color());
+        assert_eq!(data[135..138], Mark::Heat(6).color());
+        assert_eq!(data[144..147], Mark::Heat(9).color());
     }
 }
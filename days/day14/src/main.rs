@@ -1,81 +1,104 @@
-use md5;
-use std::collections::HashMap;
-
-fn find_match(
-    n: u32,
-    input: &str,
-    c: char,
-    count_repeat: usize,
+use std::collections::VecDeque;
+
+use common::md5::{Digest, Salted};
+
+/// How many hashes after a triple may confirm it as a key.
+const WINDOW: u64 = 1000;
+
+/// A hash with three of a digit in a row, and the index of the first hash
+/// after it with five of that digit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Key {
+    hash: Digest,
+    confirmation: u64,
+}
+
+/// Yields every key of the one-time pad with its index.
+///
+/// The hashes of the candidate and of the `WINDOW` indices after it are kept
+/// in a ring buffer, so that every hash is only computed once, along with
+/// the indices of those having five of a digit in a row, for every digit.
+struct KeyStream {
+    salted: Salted,
     stretch: usize,
-    hash_map: &mut HashMap<u32, String>,
-) -> Option<u32> {
-    let value = format!("{c}").repeat(count_repeat);
-    let mut result = None;
-    (1..=1000)
-        .map(|i| n + i)
-        .map(|idx| {
-            let digest = if let Some(digest) = hash_map.get(&idx) {
-                digest.clone()
-            } else {
-                let digest = (0..=stretch).fold(format!("{input}{}", idx), |digest, _| {
-                    let digest = md5::compute(digest);
-                    format!("{:x}", digest)
-                });
-                hash_map.insert(idx, digest.clone());
-                digest
-            };
-            (idx, digest)
-        })
-        .any(|(idx, digest)| {
-            if digest.contains(&value) {
-                result = Some(idx);
-                true
-            } else {
-                false
+    ring: Vec<Digest>,
+    // Where the hash of the candidate is in the ring
+    head: usize,
+    candidate: u64,
+    quintuples: [VecDeque<u64>; 16],
+}
+
+impl KeyStream {
+    fn new(salt: &str, stretch: usize) -> Self {
+        let mut stream = Self {
+            salted: Salted::new(salt),
+            stretch,
+            ring: Vec::with_capacity(WINDOW as usize + 1),
+            head: 0,
+            candidate: 0,
+            quintuples: Default::default(),
+        };
+        (0..=WINDOW).for_each(|index| {
+            let hash = stream.hash(index);
+            stream.index_quintuples(index, &hash);
+            stream.ring.push(hash);
+        });
+        stream
+    }
+
+    fn hash(&self, index: u64) -> Digest {
+        self.salted.digest(index).stretched(self.stretch)
+    }
+
+    fn index_quintuples(&mut self, index: u64, hash: &Digest) {
+        hash.runs(5).for_each(|digit| {
+            let indices = &mut self.quintuples[digit as usize];
+            if indices.back() != Some(&index) {
+                indices.push_back(index);
             }
         });
-    result
-}
+    }
 
-fn is_triple(
-    n: u32,
-    input: &str,
-    stretch: usize,
-    hash_map: &mut HashMap<u32, String>,
-) -> Option<char> {
-    let digest = if let Some(digest) = hash_map.get(&n) {
-        digest.clone()
-    } else {
-        (0..=stretch).fold(format!("{input}{n}"), |digest, _| {
-            let digest = md5::compute(digest);
-            format!("{:x}", digest)
-        })
-    };
-    let mut result = None;
-    digest.chars().collect::<Vec<_>>().windows(3).any(|c| {
-        if c[0] == c[1] && c[1] == c[2] {
-            result = Some(c[0]);
-            true
-        } else {
-            false
-        }
-    });
-    result
+    /// Moves on to the next candidate, hashing the index that enters the
+    /// window in place of the one that leaves it.
+    fn advance(&mut self) {
+        let index = self.candidate + WINDOW + 1;
+        let hash = self.hash(index);
+        self.index_quintuples(index, &hash);
+        self.ring[self.head] = hash;
+        self.head = (self.head + 1) % self.ring.len();
+        self.candidate += 1;
+    }
 }
 
-fn find_hashes(count: usize, input: &str, stretch: usize) -> Vec<u32> {
-    let mut result = vec![];
-    let mut idx = 0;
-    let mut hash_map = HashMap::new();
-    while result.len() < count {
-        if let Some(c) = is_triple(idx, input, stretch, &mut hash_map) {
-            if find_match(idx, input, c, 5, stretch, &mut hash_map).is_some() {
-                result.push(idx);
+impl Iterator for KeyStream {
+    type Item = (u64, Key);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, hash) = (self.candidate, self.ring[self.head]);
+            // Quintuples up to the candidate cannot confirm it anymore
+            self.quintuples.iter_mut().for_each(|indices| {
+                while indices.front().is_some_and(|i| *i <= index) {
+                    indices.pop_front();
+                }
+            });
+            let confirmation = hash
+                .first_run(3)
+                .and_then(|digit| self.quintuples[digit as usize].front().copied());
+            self.advance();
+            if let Some(confirmation) = confirmation {
+                return Some((index, Key { hash, confirmation }));
             }
         }
-        idx += 1;
     }
-    result
+}
+
+fn find_hashes(count: usize, input: &str, stretch: usize) -> Vec<u64> {
+    KeyStream::new(input, stretch)
+        .take(count)
+        .map(|(index, _)| index)
+        .collect()
 }
 
 fn main() {
@@ -91,12 +114,25 @@ mod day14_tests {
 
     use super::*;
 
+    fn hex_digit(nibble: u8) -> char {
+        char::from_digit(nibble as u32, 16).unwrap()
+    }
+
+    fn hash(index: u64, stretch: usize) -> Digest {
+        Salted::new("abc").digest(index).stretched(stretch)
+    }
+
+    /// The index confirming the triple of `digit` at `input`, if any.
+    fn find_match(input: u64, digit: u8, stretch: usize) -> Option<u64> {
+        (input + 1..=input + WINDOW).find(|next| hash(*next, stretch).has_run(5, digit))
+    }
+
     #[parameterized(
         input = { 1, 17, 18, 19, 25, 39, 92 },
         expected = { None, None, Some('8'), None, None, Some('e'), Some('9') }
     )]
-    fn is_triple(input: u32, expected: Option<char>) {
-        assert_eq!(is_triple(input, "abc", 0, &mut HashMap::new()), expected);
+    fn is_triple(input: u64, expected: Option<char>) {
+        assert_eq!(hash(input, 0).first_run(3).map(hex_digit), expected);
     }
 
     #[parameterized(
@@ -104,11 +140,32 @@ mod day14_tests {
         c = { '8', 'e', '9' },
         expected = { None, Some(816), Some(200) }
     )]
-    fn find_match(input: u32, c: char, expected: Option<u32>) {
-        assert_eq!(
-            find_match(input, "abc", c, 5, 0, &mut HashMap::new()),
-            expected
-        );
+    fn find_match(input: u64, c: char, expected: Option<u64>) {
+        let digit = hash(input, 0).first_run(3).unwrap();
+        assert_eq!(hex_digit(digit), c);
+        assert_eq!(find_match(input, digit, 0), expected);
+    }
+
+    #[test]
+    fn keys_match_a_plain_search() {
+        let stream = KeyStream::new("abc", 0);
+        let hashes = (0..3000)
+            .map(|index| stream.hash(index))
+            .collect::<Vec<_>>();
+        let expected = (0..hashes.len() - WINDOW as usize)
+            .filter_map(|index| {
+                let digit = hashes[index].first_run(3)?;
+                let confirmation = (index + 1..=index + WINDOW as usize)
+                    .find(|next| hashes[*next].has_run(5, digit))?;
+                Some((index as u64, confirmation as u64))
+            })
+            .collect::<Vec<_>>();
+        let keys = stream
+            .take(expected.len())
+            .map(|(index, key)| (index, key.confirmation))
+            .collect::<Vec<_>>();
+        assert!(keys.contains(&(39, 816)));
+        assert_eq!(keys, expected);
     }
 
     #[test]
@@ -122,11 +179,10 @@ mod day14_tests {
         c = { '2', 'e', 'f' },
         expected = { None, Some(89), Some(22859) }
     )]
-    fn find_match_with_stretch(input: u32, c: char, expected: Option<u32>) {
-        assert_eq!(
-            find_match(input, "abc", c, 5, 2016, &mut HashMap::new()),
-            expected
-        );
+    fn find_match_with_stretch(input: u64, c: char, expected: Option<u64>) {
+        let digit = hash(input, 2016).first_run(3).unwrap();
+        assert_eq!(hex_digit(digit), c);
+        assert_eq!(find_match(input, digit, 2016), expected);
     }
 
     #[test]
@@ -1,147 +1,320 @@
-#[derive(Debug, PartialEq)]
+use std::{
+    cmp::{self, Reverse},
+    collections::VecDeque,
+    env,
+    error::Error,
+    fmt::Display,
+    process,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Condvar, Mutex,
+    },
+    thread,
+};
+
+use common::md5::{Context, Digest};
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 enum Door {
     Open,
     Locked,
 }
 
-fn get_doors(path: &str) -> Vec<Door> {
-    let digest = md5::compute(path);
-    format!("{:x}", digest)
-        .chars()
-        .take(4)
-        .map(|c| match c {
-            'b' | 'c' | 'd' | 'e' | 'f' => Door::Open,
-            '0' | '1' | '2' | '3' | '4' | '5' | '6' | '7' | '8' | '9' | 'a' => Door::Locked,
-            char => panic!("Unexpected character {char}"),
+/// Doors from the first four hexadecimal digits of the hash of the passcode
+/// and path consumed so far, open from `b` to `f`.
+fn get_doors(context: &Context) -> Vec<Door> {
+    let digest = Digest::from(context);
+    (0..4)
+        .map(|n| match digest.nibble(n) {
+            0xb..=0xf => Door::Open,
+            _ => Door::Locked,
         })
         .collect()
 }
 
-#[derive(Debug)]
+/// Directions in the order of their doors, with how they move.
+const DIRECTIONS: [(char, (isize, isize)); 4] =
+    [('U', (0, -1)), ('D', (0, 1)), ('L', (-1, 0)), ('R', (1, 0))];
+
+/// `context` has consumed the passcode and the path, so that the hash of a
+/// longer path only costs the directions added to it.
+#[derive(Clone)]
 struct Cell {
     current_position: (usize, usize),
-    doors: Vec<Door>,
     path: String,
+    context: Context,
+}
+
+/// The shortest and longest paths to the target. Among paths of the same
+/// length, the one that comes first trying U, D, L and R in that order wins.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct BestPaths {
+    shortest: String,
+    longest: String,
 }
 
-impl From<&str> for Cell {
-    fn from(value: &str) -> Self {
-        let (pos_x, pos_y) =
-            value
-                .chars()
-                .skip_while(|c| c.is_lowercase())
-                .fold((0, 0), |(pos_x, pos_y), c| {
-                    let (dx, dy) = match c {
-                        'U' => (0, -1),
-                        'D' => (0, 1),
-                        'L' => (-1, 0),
-                        'R' => (1, 0),
-                        c => panic!("Unexpected character {c}"),
-                    };
-                    (pos_x + dx, pos_y + dy)
-                });
-        let doors = get_doors(value);
+impl BestPaths {
+    /// The same whichever order paths are merged in.
+    fn merge(self, other: Self) -> Self {
+        let rank = |path: &String| {
+            path.chars()
+                .map(|c| DIRECTIONS.iter().position(|(d, _)| *d == c).unwrap())
+                .collect::<Vec<_>>()
+        };
         Self {
-            current_position: (pos_x as usize, pos_y as usize),
-            doors,
-            path: value.to_owned(),
+            shortest: cmp::min_by_key(self.shortest, other.shortest, |path| {
+                (path.len(), rank(path))
+            }),
+            longest: cmp::min_by_key(self.longest, other.longest, |path| {
+                (Reverse(path.len()), rank(path))
+            }),
         }
     }
 }
 
-impl Cell {
-    fn movement(&self, m: char) -> Self {
-        let mut next_path = self.path.clone();
-        next_path.push(m);
-        Self::from(next_path.as_str())
+#[derive(Debug, PartialEq, Eq)]
+enum VaultError {
+    EmptyVault,
+    TargetOutside { x: usize, y: usize },
+}
+
+impl Display for VaultError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VaultError::EmptyVault => write!(f, "The vault needs at least one room"),
+            VaultError::TargetOutside { x, y } => {
+                write!(f, "Room {x},{y} is outside the vault")
+            }
+        }
     }
+}
 
-    fn get_valid_movements(&self) -> Vec<char> {
-        self.doors
-            .iter()
-            .enumerate()
-            .filter_map(|(direction, door)| match direction {
-                0 => {
-                    if self.current_position.1 > 0 {
-                        Some(('U', door))
-                    } else {
-                        None
-                    }
-                }
-                1 => {
-                    if self.current_position.1 < 3 {
-                        Some(('D', door))
-                    } else {
-                        None
-                    }
-                }
-                2 => {
-                    if self.current_position.0 > 0 {
-                        Some(('L', door))
-                    } else {
-                        None
-                    }
-                }
-                3 => {
-                    if self.current_position.0 < 3 {
-                        Some(('R', door))
-                    } else {
-                        None
+impl Error for VaultError {}
+
+#[derive(Debug, Clone)]
+struct Vault {
+    passcode: String,
+    width: usize,
+    height: usize,
+    target: (usize, usize),
+}
+
+impl Vault {
+    fn new(passcode: &str) -> Self {
+        Self {
+            passcode: passcode.to_owned(),
+            width: 4,
+            height: 4,
+            target: (3, 3),
+        }
+    }
+
+    /// Also moves the target to the bottom right room.
+    fn size(mut self, width: usize, height: usize) -> Result<Self, VaultError> {
+        if width == 0 || height == 0 {
+            return Err(VaultError::EmptyVault);
+        }
+        self.width = width;
+        self.height = height;
+        self.target = (width - 1, height - 1);
+        Ok(self)
+    }
+
+    fn target(mut self, x: usize, y: usize) -> Result<Self, VaultError> {
+        if x >= self.width || y >= self.height {
+            return Err(VaultError::TargetOutside { x, y });
+        }
+        self.target = (x, y);
+        Ok(self)
+    }
+
+    fn start(&self) -> Cell {
+        let mut context = Context::new();
+        context.consume(&self.passcode);
+        Cell {
+            current_position: (0, 0),
+            path: String::new(),
+            context,
+        }
+    }
+
+    /// Rooms behind the open doors of `cell`. Nothing leads out of the
+    /// target, reaching it ends the path.
+    fn movements(&self, cell: &Cell) -> Vec<Cell> {
+        if cell.current_position == self.target {
+            return vec![];
+        }
+        let (x, y) = cell.current_position;
+        get_doors(&cell.context)
+            .into_iter()
+            .zip(DIRECTIONS)
+            .filter(|(door, _)| *door == Door::Open)
+            .filter_map(|(_, (direction, (dx, dy)))| {
+                let position = (x.checked_add_signed(dx)?, y.checked_add_signed(dy)?);
+                (position.0 < self.width && position.1 < self.height).then(|| {
+                    let mut path = cell.path.clone();
+                    path.push(direction);
+                    let mut context = cell.context.clone();
+                    context.consume([direction as u8]);
+                    Cell {
+                        current_position: position,
+                        path,
+                        context,
                     }
-                }
-                c => panic!("Unexpected character {c}"),
-            })
-            .filter_map(|(c, door)| match door {
-                Door::Open => Some(c),
-                Door::Locked => None,
+                })
             })
             .collect()
     }
-}
 
-fn find_shortest_path(input: &str) -> String {
-    let mut queue = vec![];
-    let start = Cell::from(input);
-    queue.push(start);
-    while !queue.is_empty() {
-        let cell = queue.remove(0);
-        if cell.current_position == (3, 3) {
-            let result = cell.path.chars().skip_while(|c| c.is_lowercase()).collect();
-            return result;
-        };
-        cell.get_valid_movements().iter().for_each(|c| {
-            queue.push(cell.movement(*c));
-        });
+    /// Every path to the target, shortest first.
+    fn paths(&self) -> Paths<'_> {
+        Paths {
+            vault: self,
+            frontier: VecDeque::from([self.start()]),
+        }
     }
-    String::new()
-}
-
-fn find_longest_path(input: &str) -> usize {
-    let mut queue = vec![];
-    let start = Cell::from(input);
-    queue.push(start);
-    let mut paths = vec![];
-    while !queue.is_empty() {
-        let cell = queue.remove(0);
-        if cell.current_position == (3, 3) {
-            let result = cell
-                .path
-                .chars()
-                .skip_while(|c| c.is_lowercase())
-                .collect::<String>();
-            paths.push(result);
-            continue;
+
+    /// Explores every path on `workers` threads, each one depth first
+    /// from its own stack and only remembering the best paths. A worker out
+    /// of rooms steals the shallowest one left in another stack, which
+    /// likely leads to the most work.
+    fn best_paths(&self, workers: usize) -> Option<BestPaths> {
+        let stacks = (0..workers.max(1))
+            .map(|_| Mutex::new(VecDeque::new()))
+            .collect::<Vec<_>>();
+        stacks[0].lock().unwrap().push_back(self.start());
+        // Rooms in a stack or being explored, there is nothing left to do
+        // once it drops to zero
+        let pending = AtomicUsize::new(1);
+        // Workers without a room sleep until one is pushed or all are done.
+        // Both are signalled while holding `idle`, which sleepers hold from
+        // their last look at the stacks, so no wakeup is lost.
+        let (idle, wakeup) = (Mutex::new(()), Condvar::new());
+        let signal = || {
+            let _idle = idle.lock().unwrap();
+            wakeup.notify_all();
         };
-        cell.get_valid_movements().iter().for_each(|c| {
-            queue.push(cell.movement(*c));
-        });
+        thread::scope(|s| {
+            let handles = (0..stacks.len())
+                .map(|worker| {
+                    let (stacks, pending, idle, wakeup) = (&stacks, &pending, &idle, &wakeup);
+                    s.spawn(move || {
+                        let mut best: Option<BestPaths> = None;
+                        while pending.load(Ordering::SeqCst) > 0 {
+                            let own = stacks[worker].lock().unwrap().pop_back();
+                            let Some(cell) = own.or_else(|| steal(stacks, worker)) else {
+                                let mut guard = idle.lock().unwrap();
+                                while pending.load(Ordering::SeqCst) > 0
+                                    && stacks.iter().all(|stack| stack.lock().unwrap().is_empty())
+                                {
+                                    guard = wakeup.wait(guard).unwrap();
+                                }
+                                continue;
+                            };
+                            if cell.current_position == self.target {
+                                let found = BestPaths {
+                                    shortest: cell.path.clone(),
+                                    longest: cell.path,
+                                };
+                                best = Some(match best {
+                                    Some(best) => best.merge(found),
+                                    None => found,
+                                });
+                            } else {
+                                let next = self.movements(&cell);
+                                if !next.is_empty() {
+                                    pending.fetch_add(next.len(), Ordering::SeqCst);
+                                    stacks[worker].lock().unwrap().extend(next);
+                                    signal();
+                                }
+                            }
+                            if pending.fetch_sub(1, Ordering::SeqCst) == 1 {
+                                signal();
+                            }
+                        }
+                        best
+                    })
+                })
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .filter_map(|handle| handle.join().unwrap())
+                .reduce(BestPaths::merge)
+        })
+    }
+}
+
+/// Takes the oldest room of the first other stack that has one.
+fn steal(stacks: &[Mutex<VecDeque<Cell>>], thief: usize) -> Option<Cell> {
+    (1..stacks.len())
+        .map(|offset| (thief + offset) % stacks.len())
+        .find_map(|victim| stacks[victim].lock().unwrap().pop_front())
+}
+
+/// Walks the vault breadth first, yielding paths as they reach the target.
+struct Paths<'a> {
+    vault: &'a Vault,
+    frontier: VecDeque<Cell>,
+}
+
+impl Iterator for Paths<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(cell) = self.frontier.pop_front() {
+            if cell.current_position == self.vault.target {
+                return Some(cell.path);
+            }
+            self.frontier.extend(self.vault.movements(&cell));
+        }
+        None
+    }
+}
+
+fn find_shortest_path(vault: &Vault) -> String {
+    vault.paths().next().unwrap_or_default()
+}
+
+fn find_longest_path(vault: &Vault) -> usize {
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    vault.best_paths(workers).unwrap().longest.len()
+}
+
+/// The value of a command line option such as `--size 5x5`, as two numbers
+/// separated by `x` or `,`.
+fn parse_option(args: &[String], name: &str) -> Result<Option<(usize, usize)>, String> {
+    let Some(i) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+    let value = args
+        .get(i + 1)
+        .ok_or_else(|| format!("Missing value for {name}"))?;
+    value
+        .split_once(['x', ','])
+        .and_then(|(a, b)| Some((a.parse().ok()?, b.parse().ok()?)))
+        .map(Some)
+        .ok_or_else(|| format!("Invalid value `{value}` for {name}"))
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args = env::args().collect::<Vec<_>>();
+    let mut vault = Vault::new("dmypynyp");
+    if let Some((width, height)) = parse_option(&args, "--size")? {
+        vault = vault.size(width, height)?;
     }
-    paths.iter().map(|s| s.len()).max().unwrap()
+    if let Some((x, y)) = parse_option(&args, "--target")? {
+        vault = vault.target(x, y)?;
+    }
+    println!("Part 1 = {}", find_shortest_path(&vault));
+    println!("Part 2 = {}", find_longest_path(&vault));
+    Ok(())
 }
 
 fn main() {
-    println!("Part 1 = {}", find_shortest_path("dmypynyp"));
-    println!("Part 2 = {}", find_longest_path("dmypynyp"));
+    if let Err(error) = run() {
+        eprintln!("{error}");
+        process::exit(1);
+    }
 }
 
 #[cfg(test)]
@@ -155,7 +328,16 @@ mod day17_tests {
         expected = { vec![Door::Open, Door::Open, Door::Open, Door::Locked], vec![Door::Open, Door::Locked, Door::Open, Door::Open], vec![Door::Locked, Door::Locked, Door::Locked, Door::Locked], vec![Door::Locked, Door::Locked, Door::Locked, Door::Open], vec![Door::Locked, Door::Locked, Door::Locked, Door::Locked] }
     )]
     fn test_get_doors(input: &str, expected: Vec<Door>) {
-        assert_eq!(get_doors(input), expected);
+        let mut context = Context::new();
+        context.consume(input);
+        assert_eq!(get_doors(&context), expected);
+        // Consuming the path one direction at a time hashes the same
+        let (passcode, path) = input.split_at(5);
+        let mut context = Context::new();
+        context.consume(passcode);
+        path.bytes()
+            .for_each(|direction| context.consume([direction]));
+        assert_eq!(get_doors(&context), expected);
     }
 
     #[parameterized(
@@ -163,7 +345,7 @@ mod day17_tests {
         expected = { "DDRRRD", "DDUDRLRRUDRD", "DRURDRUDDLLDLUURRDULRLDUUDDDRR" }
     )]
     fn part1(input: &str, expected: &str) {
-        assert_eq!(find_shortest_path(input), expected);
+        assert_eq!(find_shortest_path(&Vault::new(input)), expected);
     }
 
     #[parameterized(
@@ -171,6 +353,91 @@ mod day17_tests {
         expected = { 370, 492, 830 }
     )]
     fn part2(input: &str, expected: usize) {
-        assert_eq!(find_longest_path(input), expected);
+        assert_eq!(find_longest_path(&Vault::new(input)), expected);
+    }
+
+    /// Where a path ends, or None if it leaves a vault of that size.
+    fn walk(path: &str, width: usize, height: usize) -> Option<(usize, usize)> {
+        path.chars().try_fold((0, 0), |(x, y): (usize, usize), c| {
+            let (_, (dx, dy)) = DIRECTIONS.iter().find(|(d, _)| *d == c).unwrap();
+            let position = (x.checked_add_signed(*dx)?, y.checked_add_signed(*dy)?);
+            (position.0 < width && position.1 < height).then_some(position)
+        })
+    }
+
+    #[parameterized(
+        passcode = { "ihgpwlah", "kglvqrro", "ulqzkmiv", "ihgpwlah", "dmypynyp" },
+        size = { (3, 4), (4, 3), (5, 3), (4, 4), (4, 4) },
+        target = { (2, 3), (3, 2), (4, 2), (3, 0), (0, 3) },
+        shortest = { "DDRRD", "DDUDRLRRUDR", "DRURDRUDDR", "DRRULDDULRURR", "RDRDUDDLL" },
+        longest = { 27, 73, 10, 51, 149 }
+    )]
+    fn test_vault(
+        passcode: &str,
+        size: (usize, usize),
+        target: (usize, usize),
+        shortest: &str,
+        longest: usize,
+    ) {
+        let vault = Vault::new(passcode)
+            .size(size.0, size.1)
+            .and_then(|vault| vault.target(target.0, target.1))
+            .unwrap();
+        let paths = vault.paths().collect::<Vec<_>>();
+        assert!(!paths.is_empty());
+        assert!(paths.windows(2).all(|w| w[0].len() <= w[1].len()));
+        assert!(paths
+            .iter()
+            .all(|path| walk(path, size.0, size.1) == Some(target)));
+        let best = vault.best_paths(1).unwrap();
+        // Any number of threads finds the same paths
+        [2, 3, 8].into_iter().for_each(|workers| {
+            assert_eq!(vault.best_paths(workers), Some(best.clone()));
+        });
+        assert_eq!(best.shortest, shortest);
+        assert_eq!(best.longest.len(), longest);
+        assert_eq!(best.shortest, paths[0]);
+        assert_eq!(best.longest.len(), paths.last().unwrap().len());
+        assert_eq!(
+            Some(&best.longest),
+            paths.iter().find(|path| path.len() == best.longest.len())
+        );
+    }
+
+    #[test]
+    fn test_vault_defaults() {
+        let vault = Vault::new("ihgpwlah");
+        assert_eq!(vault.clone().size(4, 4).unwrap().target, vault.target);
+        assert_eq!(vault.paths().next().unwrap(), "DDRRRD");
+        assert_eq!(vault.best_paths(4).unwrap().longest.len(), 370);
+        assert_eq!(Vault::new("hijkl").paths().next(), None);
+        assert_eq!(Vault::new("hijkl").best_paths(4), None);
+    }
+
+    #[test]
+    fn test_vault_errors() {
+        let vault = Vault::new("ihgpwlah");
+        assert_eq!(vault.clone().size(0, 4).err(), Some(VaultError::EmptyVault));
+        assert_eq!(vault.clone().size(4, 0).err(), Some(VaultError::EmptyVault));
+        assert_eq!(
+            vault.clone().target(4, 0).err(),
+            Some(VaultError::TargetOutside { x: 4, y: 0 })
+        );
+        assert_eq!(
+            vault.size(2, 2).unwrap().target(1, 2).err(),
+            Some(VaultError::TargetOutside { x: 1, y: 2 })
+        );
+    }
+
+    #[parameterized(
+        args = { "day17", "day17 --size 5x3", "day17 --size 5,3", "day17 --size", "day17 --size 5", "day17 --size ax3", "day17 --size 5x-3" },
+        expected = { Ok(None), Ok(Some((5, 3))), Ok(Some((5, 3))), Err("Missing value for --size"), Err("Invalid value `5` for --size"), Err("Invalid value `ax3` for --size"), Err("Invalid value `5x-3` for --size") }
+    )]
+    fn test_parse_option(args: &str, expected: Result<Option<(usize, usize)>, &str>) {
+        let args = args.split(' ').map(str::to_owned).collect::<Vec<_>>();
+        assert_eq!(
+            parse_option(&args, "--size"),
+            expected.map_err(str::to_owned)
+        );
     }
 }
@@ -1,22 +1,18 @@
 use common::read_input;
 
-fn check_trap(prev: &str) -> bool {
-    let left = prev.chars().nth(0).unwrap();
-    let center = prev.chars().nth(1).unwrap();
-    let right = prev.chars().nth(2).unwrap();
-    let first_rule = left == '^' && center == '^' && right == '.';
-    let second_rule = left == '.' && center == '^' && right == '^';
-    let third_rule = left == '^' && center == '.' && right == '.';
-    let fourth_rule = left == '.' && center == '.' && right == '^';
-    first_rule || second_rule || third_rule || fourth_rule
+fn check_trap(prev: &[char]) -> bool {
+    matches!(
+        prev,
+        ['^', '^', '.'] | ['.', '^', '^'] | ['^', '.', '.'] | ['.', '.', '^']
+    )
 }
 
-fn next_row(from: &str) -> String {
+fn next_row(from: &[char]) -> Vec<char> {
     let row_len = from.len();
     (0..row_len)
         .map(|i| match i {
-            0 => from.chars().nth(1).unwrap() == '^',
-            n if n == row_len - 1 => from.chars().nth(row_len - 2).unwrap() == '^',
+            0 => from[1] == '^',
+            n if n == row_len - 1 => from[row_len - 2] == '^',
             n => check_trap(&from[n - 1..n + 2]),
         })
         .map(|tile| match tile {
@@ -26,39 +22,43 @@ fn next_row(from: &str) -> String {
         .collect()
 }
 
-fn produce_map(from: &str, rows: usize) -> String {
-    let map = (0..rows).fold(vec![String::from(from.trim())], |mut v, _| {
-        let next = next_row(v.last().unwrap());
-        v.push(next);
-        v
+/// Safe tiles in the first row and the `rows` generated after it, keeping
+/// only the latest row around.
+fn count_safe(from: &str, rows: usize) -> usize {
+    let safe = |row: &[char]| row.iter().filter(|c| **c == '.').count();
+    let mut row = from.trim().chars().collect::<Vec<_>>();
+    let mut count = safe(&row);
+    (0..rows).for_each(|_| {
+        row = next_row(&row);
+        count += safe(&row);
     });
-    map.join("\n")
-}
-
-fn count_safe(map: &str) -> usize {
-    map.lines()
-        .map(|l| l.chars().filter(|c| *c == '.').count())
-        .sum()
+    count
 }
 
 fn main() {
     let input = read_input("day18.txt");
-    let map = produce_map(input.as_str(), 39);
-    println!("Part 1 = {}", count_safe(&map));
-    let map = produce_map(input.as_str(), 399999);
-    println!("Part 2 = {}", count_safe(&map));
+    println!("Part 1 = {}", count_safe(input.as_str(), 39));
+    println!("Part 2 = {}", count_safe(input.as_str(), 399999));
 }
 
 #[cfg(test)]
 mod day18_tests {
+    use common::grid::Grid;
+
     use super::*;
 
+    fn produce_map(from: &str, rows: usize) -> Grid<char> {
+        let mut map = Grid::<char>::from(from.trim());
+        (0..rows).for_each(|y| map.push_row(next_row(map.row(y))));
+        map
+    }
+
     #[test]
     fn test_next_row() {
-        let next = next_row("..^^.");
-        assert_eq!(next, ".^^^^".to_owned());
+        let next = next_row(&"..^^.".chars().collect::<Vec<_>>());
+        assert_eq!(next.iter().collect::<String>(), ".^^^^".to_owned());
         let next = next_row(&next);
-        assert_eq!(next, "^^..^".to_owned());
+        assert_eq!(next.iter().collect::<String>(), "^^..^".to_owned());
     }
 
     #[test]
@@ -74,13 +74,14 @@ mod day18_tests {
 .^^^..^.^^
 ^^.^^^..^^"#;
         let input = ".^^.^.^^^^";
-        assert_eq!(produce_map(input, 9), expected.to_owned());
+        assert_eq!(produce_map(input, 9).to_string(), expected.to_owned());
     }
 
     #[test]
     fn part1() {
         let input = ".^^.^.^^^^";
+        assert_eq!(count_safe(input, 9), 38);
         let map = produce_map(input, 9);
-        assert_eq!(count_safe(&map), 38);
+        assert_eq!(map.iter().filter(|(_, c)| **c == '.').count(), 38);
     }
 }
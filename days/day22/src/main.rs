@@ -1,11 +1,63 @@
 use std::{
-    cmp::Reverse,
-    collections::{BinaryHeap, HashSet},
-    rc::Rc,
+    collections::{HashMap, HashSet},
+    env,
+    error::Error,
+    fmt::Display,
 };
 
-use common::read_input;
+use common::{
+    grid::Grid,
+    read_input,
+    search::{astar, bfs_distances},
+};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+/// Megabytes in every unit of `df -h` output, largest first.
+const UNITS: [(char, usize); 3] = [('T', 1 << 20), ('G', 1 << 10), ('M', 1)];
+
+fn parse_size(text: &str) -> Result<usize, String> {
+    // `df -h` leaves the unit out of empty columns
+    if text == "0" {
+        return Ok(0);
+    }
+    let (unit, megabytes) = UNITS
+        .iter()
+        .find(|(unit, _)| text.ends_with(*unit))
+        .ok_or_else(|| format!("`{text}` is not a size in T, G or M"))?;
+    let amount = text
+        .strip_suffix(*unit)
+        .unwrap()
+        .parse::<f64>()
+        .ok()
+        .filter(|amount| amount.is_finite() && *amount >= 0.0)
+        .ok_or_else(|| format!("`{text}` is not a size in T, G or M"))?;
+    Ok((amount * *megabytes as f64).round() as usize)
+}
+
+/// Writes a size in the largest unit that divides it.
+fn format_size(size: usize) -> String {
+    let (unit, megabytes) = UNITS
+        .iter()
+        .find(|(_, megabytes)| size.is_multiple_of(*megabytes))
+        .unwrap();
+    format!("{}{unit}", size / megabytes)
+}
 
+/// Where a node is, from a file system name ending in `-x<column>-y<row>`.
+fn parse_coordinates(filesystem: &str) -> Result<(usize, usize), String> {
+    let mut parts = filesystem.rsplit('-');
+    let y = parts.next().and_then(|y| y.strip_prefix('y'));
+    let x = parts.next().and_then(|x| x.strip_prefix('x'));
+    match (x.map(str::parse), y.map(str::parse)) {
+        (Some(Ok(x)), Some(Ok(y))) => Ok((x, y)),
+        _ => Err(format!(
+            "`{filesystem}` does not name a node as in node-x0-y0"
+        )),
+    }
+}
+
+/// Sizes are in megabytes.
 #[derive(Debug, Clone, Ord, Eq, PartialOrd)]
 struct Node {
     x: usize,
@@ -20,34 +72,51 @@ impl PartialEq for Node {
     }
 }
 
-impl From<&str> for Node {
-    fn from(value: &str) -> Self {
-        let mut split = value.split_whitespace().filter(|s| !s.is_empty());
-        let fs = split.next().unwrap();
-        let mut partial_fs = fs.split('-');
-        partial_fs.next().unwrap();
-        let (x, y) = (partial_fs.next().unwrap(), partial_fs.next().unwrap());
-        let x = x.strip_prefix('x').unwrap().parse().unwrap();
-        let y = y.strip_prefix('y').unwrap().parse().unwrap();
-        let size = split
-            .next()
-            .unwrap()
-            .strip_suffix('T')
-            .unwrap()
-            .parse()
-            .unwrap();
-        let used = split
-            .next()
-            .unwrap()
-            .strip_suffix('T')
-            .unwrap()
-            .parse()
-            .unwrap();
-        Self { x, y, size, used }
+/// One line of `df -h`: the file system, its size, used and available
+/// space, then optionally the use percentage and where it is mounted.
+impl TryFrom<&str> for Node {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let fields = value.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 4 {
+            return Err("expected a file system, its size, used and available space".to_owned());
+        }
+        Self::from_columns(
+            fields[0],
+            fields[1],
+            fields[2],
+            Some(fields[3]),
+            fields.get(4).copied(),
+        )
     }
 }
 
 impl Node {
+    /// The available space and use percentage are rounded by `df -h`, so
+    /// they are only checked to be well formed.
+    fn from_columns(
+        filesystem: &str,
+        size: &str,
+        used: &str,
+        avail: Option<&str>,
+        use_percent: Option<&str>,
+    ) -> Result<Self, String> {
+        let (x, y) = parse_coordinates(filesystem)?;
+        let (size, used) = (parse_size(size)?, parse_size(used)?);
+        if used > size {
+            return Err(format!("node x={x},y={y} uses more than its size"));
+        }
+        avail.map(parse_size).transpose()?;
+        if let Some(percent) = use_percent {
+            percent
+                .strip_suffix('%')
+                .and_then(|percent| percent.parse::<f64>().ok())
+                .ok_or_else(|| format!("`{percent}` is not a percentage"))?;
+        }
+        Ok(Self { x, y, size, used })
+    }
+
     fn avail(&self) -> usize {
         self.size - self.used
     }
@@ -65,214 +134,646 @@ impl Node {
     }
 }
 
+/// Where the goal data is and how much every node stores, in reading order.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct ClusterState {
-    data_x: usize,
-    data_y: usize,
-    zero_x: usize,
-    zero_y: usize,
+    goal: (usize, usize),
+    used: Vec<usize>,
 }
 
-impl From<&Cluster> for ClusterState {
-    fn from(value: &Cluster) -> Self {
-        let (zero_x, zero_y) = value.get_zero_pos();
-        Self {
-            data_x: value.data_x,
-            data_y: value.data_y,
-            zero_x,
-            zero_y,
-        }
-    }
+/// Where the goal data and the empty nodes are. That is all there is to know
+/// about a cluster whose data is interchangeable.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct HoleState {
+    goal: (usize, usize),
+    holes: Vec<(usize, usize)>,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+/// All the data of a node going to one of its neighbors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Move {
+    from: (usize, usize),
+    to: (usize, usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Cluster {
-    nodes: Rc<Vec<Node>>,
-    rows: usize,
-    cols: usize,
-    data_x: usize,
-    data_y: usize,
+    nodes: Grid<Node>,
+    /// Every node, from the least available space to the most.
+    by_avail: Vec<(usize, usize)>,
 }
 
-impl From<&str> for Cluster {
-    fn from(value: &str) -> Self {
-        let nodes = value
-            .trim()
-            .lines()
-            .skip(2)
-            .map(|l| l.into())
-            .collect::<Vec<Node>>();
-        let (rows, cols) = nodes.iter().fold((1, 1), |(rows, cols), node| {
-            let cols = if node.x > cols { node.x + 1 } else { cols };
-            let rows = if node.y > rows { node.y + 1 } else { rows };
-            (rows, cols)
-        });
-        Self {
-            nodes: nodes.into(),
-            rows,
-            cols,
-            data_x: cols - 1,
-            data_y: 0,
+/// A node as listed by `findmnt --df --json`, or by any JSON with the same
+/// columns as `df -h`.
+#[derive(Deserialize)]
+struct JsonNode {
+    #[serde(alias = "source")]
+    filesystem: String,
+    size: String,
+    used: String,
+    avail: Option<String>,
+    #[serde(rename = "use%")]
+    use_percent: Option<String>,
+}
+
+impl TryFrom<JsonNode> for Node {
+    type Error = String;
+
+    fn try_from(value: JsonNode) -> Result<Self, Self::Error> {
+        Self::from_columns(
+            &value.filesystem,
+            &value.size,
+            &value.used,
+            value.avail.as_deref(),
+            value.use_percent.as_deref(),
+        )
+    }
+}
+
+/// The listing `findmnt --df --json` prints.
+#[derive(Deserialize)]
+struct JsonListing<'a> {
+    #[serde(borrow)]
+    filesystems: Vec<&'a RawValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseError {
+    Line { line: usize, reason: String },
+    MissingNode { x: usize, y: usize },
+    NoNodes,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Line { line, reason } => write!(f, "Line {line}: {reason}"),
+            ParseError::MissingNode { x, y } => write!(f, "Node x={x},y={y} is missing"),
+            ParseError::NoNodes => write!(f, "No nodes listed"),
         }
     }
 }
 
-fn heuristic(data_x: usize, data_y: usize) -> usize {
-    data_x + data_y
+impl Error for ParseError {}
+
+/// A node, or why it could not be read, with the number of the line it is
+/// on.
+type Row = (usize, Result<Node, String>);
+
+/// The message of a JSON error, without the position it ends with.
+fn json_reason(error: &serde_json::Error) -> String {
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    let reason = error.to_string();
+    reason.strip_suffix(&position).unwrap_or(&reason).to_owned()
+}
+
+/// Reads `df -h` output, skipping whatever comes before the first node, or
+/// a JSON listing of nodes.
+impl TryFrom<&str> for Cluster {
+    type Error = ParseError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let rows = match value.trim_start().chars().next() {
+            Some('[' | '{') => Self::json_rows(value)?,
+            _ => Self::df_rows(value),
+        };
+        let mut seen = HashSet::new();
+        let nodes = rows
+            .into_iter()
+            .map(|(line, node)| {
+                let error = |reason| ParseError::Line { line, reason };
+                let node = node.map_err(error)?;
+                match seen.insert((node.x, node.y)) {
+                    true => Ok(node),
+                    false => Err(error(format!(
+                        "node x={},y={} is listed twice",
+                        node.x, node.y
+                    ))),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::from_nodes(nodes)
+    }
 }
 
 impl Cluster {
-    fn viable_pairs(&self) -> Vec<(Node, Node)> {
-        self.nodes
-            .iter()
-            .flat_map(|node| {
-                vec![node.clone(); self.nodes.len()]
-                    .into_iter()
-                    .zip(self.nodes.iter().cloned())
-                    .collect::<Vec<(Node, Node)>>()
+    /// Every node line with its number. Headers come before the first node,
+    /// blank lines can be anywhere.
+    fn df_rows(value: &str) -> Vec<Row> {
+        let mut rows = vec![];
+        value.lines().enumerate().for_each(|(n, line)| {
+            let fields = line.split_whitespace().collect::<Vec<_>>();
+            let is_node = fields.len() >= 4 && fields[1].starts_with(|c: char| c.is_ascii_digit());
+            if !fields.is_empty() && (is_node || !rows.is_empty()) {
+                rows.push((n + 1, Node::try_from(line)));
+            }
+        });
+        rows
+    }
+
+    /// Every node of a JSON list, or of a `findmnt` listing, with the line
+    /// it starts on.
+    fn json_rows(value: &str) -> Result<Vec<Row>, ParseError> {
+        let entries = match value.trim_start().starts_with('[') {
+            true => serde_json::from_str::<Vec<&RawValue>>(value),
+            false => serde_json::from_str::<JsonListing>(value).map(|listing| listing.filesystems),
+        }
+        .map_err(|error| ParseError::Line {
+            line: error.line(),
+            reason: json_reason(&error),
+        })?;
+        let rows = entries
+            .into_iter()
+            .map(|entry| {
+                let offset = entry.get().as_ptr() as usize - value.as_ptr() as usize;
+                let line = value[..offset].matches('\n').count() + 1;
+                match serde_json::from_str::<JsonNode>(entry.get()) {
+                    Ok(node) => (line, Node::try_from(node)),
+                    Err(error) => (line + error.line() - 1, Err(json_reason(&error))),
+                }
             })
-            .filter(|(node, other)| node.is_viable_pair(other))
-            .collect()
+            .collect();
+        Ok(rows)
+    }
+
+    /// Nodes have to fill a rectangle from x=0,y=0.
+    fn from_nodes(mut nodes: Vec<Node>) -> Result<Self, ParseError> {
+        let cols = nodes
+            .iter()
+            .map(|node| node.x + 1)
+            .max()
+            .ok_or(ParseError::NoNodes)?;
+        let rows = nodes.iter().map(|node| node.y + 1).max().unwrap();
+        nodes.sort_by_key(|node| (node.y, node.x));
+        if let Some((x, y)) = (0..rows)
+            .flat_map(|y| (0..cols).map(move |x| (x, y)))
+            .zip(
+                nodes
+                    .iter()
+                    .map(|node| (node.x, node.y))
+                    .chain(std::iter::repeat((cols, rows))),
+            )
+            .find_map(|(expected, found)| (expected != found).then_some(expected))
+        {
+            return Err(ParseError::MissingNode { x, y });
+        }
+        let nodes = Grid::from_vec(cols, rows, nodes);
+        let mut by_avail = nodes.positions().collect::<Vec<_>>();
+        by_avail.sort_by_key(|position| nodes[*position].avail());
+        Ok(Self { nodes, by_avail })
     }
 
-    fn viable_neighbors(&self) -> Vec<(Node, Node)> {
+    /// Nodes with room for the data of `node`, itself included.
+    fn fitting(&self, node: &Node) -> &[(usize, usize)] {
+        let first = self
+            .by_avail
+            .partition_point(|position| self.nodes[*position].avail() < node.used);
+        &self.by_avail[first..]
+    }
+
+    fn viable_pairs(&self) -> impl Iterator<Item = (&Node, &Node)> {
         self.nodes
             .iter()
-            .filter(|node| node.used == 0)
-            .flat_map(|node| {
-                let others = self
-                    .neighbors(node)
-                    .into_iter()
-                    .cloned()
-                    .filter(|neighbor| neighbor.is_viable_pair(node))
-                    .collect::<Vec<_>>();
-                vec![node.clone(); others.len()]
-                    .into_iter()
-                    .zip(others)
-                    .collect::<Vec<(Node, Node)>>()
+            .filter(|(_, node)| !node.is_empty())
+            .flat_map(move |(_, node)| {
+                self.fitting(node)
+                    .iter()
+                    .map(move |position| (node, &self.nodes[*position]))
+                    .filter(|(node, other)| node.is_viable_pair(other))
             })
-            .collect()
     }
 
-    fn get_node(&self, x: usize, y: usize) -> &Node {
+    fn count_viable_pairs(&self) -> usize {
         self.nodes
             .iter()
-            .find(|node| node.x == x && node.y == y)
-            .unwrap()
+            .filter(|(_, node)| !node.is_empty())
+            .map(|(_, node)| self.fitting(node).len() - usize::from(node.will_fit(node)))
+            .sum()
+    }
+
+    fn index(&self, (x, y): (usize, usize)) -> usize {
+        y * self.nodes.width() + x
+    }
+
+    fn position(&self, index: usize) -> (usize, usize) {
+        (index % self.nodes.width(), index / self.nodes.width())
+    }
+
+    fn initial_state(&self) -> ClusterState {
+        ClusterState {
+            goal: (self.nodes.width() - 1, 0),
+            used: self.nodes.iter().map(|(_, node)| node.used).collect(),
+        }
     }
 
-    fn get_zero_pos(&self) -> (usize, usize) {
+    /// Every state one move away: all the data of a node can go to a
+    /// neighbor with enough space left, even if that one is not empty.
+    fn next_states(&self, state: &ClusterState) -> Vec<ClusterState> {
         self.nodes
-            .iter()
-            .filter(|node| node.used == 0)
-            .map(|node| (node.x, node.y))
-            .nth(0)
-            .unwrap()
+            .positions()
+            .filter(|from| state.used[self.index(*from)] > 0)
+            .flat_map(|from| {
+                self.nodes.neighbors4(from).filter_map(move |to| {
+                    let (from_index, to_index) = (self.index(from), self.index(to));
+                    if state.used[from_index] > self.nodes[to].size - state.used[to_index] {
+                        return None;
+                    }
+                    Some(self.apply(state, &Move { from, to }))
+                })
+            })
+            .collect()
     }
 
-    fn neighbors(&self, node: &Node) -> Vec<&Node> {
-        let mut neighbors = vec![];
-        if node.x > 0 {
-            neighbors.push(self.get_node(node.x - 1, node.y));
+    fn apply(&self, state: &ClusterState, step: &Move) -> ClusterState {
+        let (from_index, to_index) = (self.index(step.from), self.index(step.to));
+        let mut next = state.clone();
+        next.used[to_index] += next.used[from_index];
+        next.used[from_index] = 0;
+        if next.goal == step.from {
+            next.goal = step.to;
         }
-        if node.y > 0 {
-            neighbors.push(self.get_node(node.x, node.y - 1));
+        next
+    }
+
+    /// Nodes whose data fits in the smallest empty node, the only ones that
+    /// can ever move when the data is interchangeable.
+    fn movable(&self) -> Grid<bool> {
+        let smallest_hole = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.is_empty())
+            .map(|(_, node)| node.size)
+            .min()
+            .unwrap_or(0);
+        self.nodes.map(|node| node.used <= smallest_hole)
+    }
+
+    /// Whether only the empty nodes matter. That is the case when the data
+    /// of every movable node fits in any other movable node once emptied, no
+    /// node can hold the data of two movable nodes, and the data of the
+    /// other nodes can neither move nor make room for more.
+    fn has_interchangeable_data(&self) -> bool {
+        let movable = self.movable();
+        let (normal, walls): (Vec<_>, Vec<_>) = self
+            .nodes
+            .iter()
+            .partition(|(position, _)| movable[*position]);
+        if !self.nodes.iter().any(|(_, node)| node.is_empty())
+            || !movable[self.initial_state().goal]
+        {
+            return false;
         }
-        if node.x < self.cols - 1 {
-            neighbors.push(self.get_node(node.x + 1, node.y));
+        let max_used = normal.iter().map(|(_, n)| n.used).max().unwrap();
+        let min_size = normal.iter().map(|(_, n)| n.size).min().unwrap();
+        let max_size = normal.iter().map(|(_, n)| n.size).max().unwrap();
+        let min_data = normal
+            .iter()
+            .filter(|(_, n)| !n.is_empty())
+            .map(|(_, n)| n.used)
+            .min()
+            .unwrap_or(usize::MAX);
+        let max_wall_avail = walls.iter().map(|(_, n)| n.avail()).max().unwrap_or(0);
+        max_used <= min_size
+            && max_size < min_data.saturating_mul(2)
+            && walls.iter().all(|(_, wall)| {
+                wall.avail() < min_data && wall.used > max_size.max(max_wall_avail)
+            })
+    }
+
+    /// Moves of an empty node swapping places with the data of a movable
+    /// neighbor.
+    fn next_hole_states(&self, movable: &Grid<bool>, state: &HoleState) -> Vec<HoleState> {
+        state
+            .holes
+            .iter()
+            .enumerate()
+            .flat_map(|(n, hole)| {
+                self.nodes
+                    .neighbors4(*hole)
+                    .filter(|from| movable[*from] && !state.holes.contains(from))
+                    .map(move |from| {
+                        let mut next = state.clone();
+                        next.holes[n] = from;
+                        next.holes.sort();
+                        if next.goal == from {
+                            next.goal = *hole;
+                        }
+                        next
+                    })
+            })
+            .collect()
+    }
+
+    /// Steps for an empty node to reach every movable node from the goal
+    /// data, going around the unmovable ones.
+    fn reach(&self, movable: &Grid<bool>, goal: (usize, usize)) -> HashMap<(usize, usize), usize> {
+        bfs_distances(goal, usize::MAX, |&current| {
+            self.nodes
+                .neighbors4(current)
+                .filter(|next| movable[*next])
+                .collect::<Vec<_>>()
+        })
+    }
+
+    /// The goal data has to move at least once per step away from the
+    /// target, and some empty node must first get next to it.
+    ///
+    /// With a single empty node, bringing it back in front of the goal data
+    /// between two of its moves takes two moves when the goal data turns and
+    /// four when it keeps going straight. Detours away from the target cost
+    /// more than the turns they allow, and a path can turn at most twice per
+    /// step along its shorter side.
+    fn min_remaining_moves(
+        &self,
+        reach: &HashMap<(usize, usize), usize>,
+        state: &HoleState,
+    ) -> usize {
+        let distance = state.goal.0 + state.goal.1;
+        if distance == 0 {
+            return 0;
         }
-        if node.y < self.rows - 1 {
-            neighbors.push(self.get_node(node.x, node.y + 1));
+        let approach = state
+            .holes
+            .iter()
+            .filter_map(|hole| reach.get(hole))
+            .min()
+            .map_or(0, |steps| steps - 1);
+        match state.holes.len() {
+            1 => {
+                let turns = (distance - 1).min(2 * state.goal.0.min(state.goal.1));
+                approach + distance + 2 * turns + 4 * (distance - 1 - turns)
+            }
+            _ => approach + distance,
         }
-        neighbors
     }
 
-    fn move_data(&self, from: &Node, to: &Node) -> Cluster {
-        let new_from = Node {
-            used: 0,
-            ..from.clone()
-        };
-        let new_to = Node {
-            used: to.used + from.used,
-            ..to.clone()
-        };
-        let (data_x, data_y) = if from.x == self.data_x && from.y == self.data_y {
-            (to.x, to.y)
+    /// The moves bringing the goal data to the target, as few as possible.
+    fn move_data_to_goal(&self) -> Option<Vec<Move>> {
+        if self.has_interchangeable_data() {
+            self.move_holes_to_goal()
         } else {
-            (self.data_x, self.data_y)
-        };
-        let mut new_nodes = (*self.nodes).clone();
-        new_nodes.iter_mut().for_each(|node| {
-            if *node == *from {
-                *node = new_from.clone();
-            }
-            if *node == *to {
-                *node = new_to.clone();
-            }
-        });
-
-        Cluster {
-            nodes: new_nodes.into(),
-            data_x,
-            data_y,
-            ..self.clone()
+            self.search_all_moves()
         }
     }
 
-    fn move_data_to_goal(&self) -> usize {
-        let mut queue = BinaryHeap::new();
-        let mut visited: HashSet<ClusterState> = HashSet::new();
-        let start_state: ClusterState = self.into();
-        queue.push((
-            Reverse(heuristic(self.data_x, self.data_y)),
-            0,
-            self.clone(),
-        ));
-        visited.insert(start_state);
-
-        while let Some((_, depth, cluster)) = queue.pop() {
-            if cluster.data_x == 0 && cluster.data_y == 0 {
-                return depth;
-            }
-            cluster.viable_neighbors().iter().for_each(|(from, to)| {
-                let new_cluster = cluster.move_data(to, from);
-                let new_cluster_state = (&new_cluster).into();
-                if visited.insert(new_cluster_state) {
-                    let new_depth = depth + 1;
-                    let priority = new_depth + heuristic(new_cluster.data_x, new_cluster.data_y);
-                    queue.push((Reverse(priority), new_depth, new_cluster));
+    fn count_moves(&self) -> Option<usize> {
+        self.move_data_to_goal().map(|moves| moves.len())
+    }
+
+    fn move_holes_to_goal(&self) -> Option<Vec<Move>> {
+        let movable = self.movable();
+        let mut holes = self
+            .nodes
+            .iter()
+            .filter(|(_, node)| node.is_empty())
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        holes.sort();
+        let start = HoleState {
+            goal: self.initial_state().goal,
+            holes,
+        };
+        // Distances from the goal data only depend on where it is
+        let mut reaches = HashMap::new();
+        let path = astar(
+            start,
+            |state| {
+                self.next_hole_states(&movable, state)
+                    .into_iter()
+                    .map(|next| (next, 1))
+            },
+            |state| {
+                let reach = reaches
+                    .entry(state.goal)
+                    .or_insert_with(|| self.reach(&movable, state.goal));
+                self.min_remaining_moves(reach, state)
+            },
+            |state| state.goal == (0, 0),
+        )?;
+        // The data moves into the hole that was left, from the one that
+        // appeared
+        let moves = path
+            .states
+            .windows(2)
+            .map(|w| {
+                let to = w[0].holes.iter().find(|hole| !w[1].holes.contains(hole));
+                let from = w[1].holes.iter().find(|hole| !w[0].holes.contains(hole));
+                Move {
+                    from: *from.unwrap(),
+                    to: *to.unwrap(),
                 }
-            });
+            })
+            .collect();
+        Some(moves)
+    }
+
+    fn search_all_moves(&self) -> Option<Vec<Move>> {
+        let path = astar(
+            self.initial_state(),
+            |state| self.next_states(state).into_iter().map(|next| (next, 1)),
+            |state| state.goal.0 + state.goal.1,
+            |state| state.goal == (0, 0),
+        )?;
+        // The node left empty gave its data to the one that got fuller
+        let moves = path
+            .states
+            .windows(2)
+            .map(|w| {
+                let from = (0..w[0].used.len()).find(|i| w[0].used[*i] > 0 && w[1].used[*i] == 0);
+                let to = (0..w[0].used.len()).find(|i| w[1].used[*i] > w[0].used[*i]);
+                Move {
+                    from: self.position(from.unwrap()),
+                    to: self.position(to.unwrap()),
+                }
+            })
+            .collect();
+        Some(moves)
+    }
+
+    /// Nodes whose data is too large to ever fit in an empty node. Without
+    /// any empty node there is no telling, so no node is a wall.
+    fn walls(&self) -> Grid<bool> {
+        if self.nodes.iter().any(|(_, node)| node.is_empty()) {
+            self.movable().map(|movable| !movable)
+        } else {
+            self.nodes.map(|_| false)
         }
-        0
+    }
+
+    /// Draws the cluster the way the puzzle does: `_` for empty nodes, `#`
+    /// for walls, `G` for the goal data and parentheses around the target.
+    fn render(&self, state: &ClusterState) -> String {
+        let walls = self.walls();
+        (0..self.nodes.height())
+            .map(|y| {
+                let row = (0..self.nodes.width())
+                    .map(|x| {
+                        let symbol = if state.goal == (x, y) {
+                            'G'
+                        } else if state.used[self.index((x, y))] == 0 {
+                            '_'
+                        } else if walls[(x, y)] {
+                            '#'
+                        } else {
+                            '.'
+                        };
+                        match (x, y) {
+                            (0, 0) => format!("({symbol})"),
+                            _ => format!(" {symbol} "),
+                        }
+                    })
+                    .collect::<String>();
+                row.trim_end().to_owned()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Lists every state of a solution, each one preceded by the move that
+    /// leads to it.
+    fn replay(&self, moves: &[Move]) -> String {
+        let mut current = self.initial_state();
+        let mut frames = vec![self.render(&current)];
+        moves.iter().enumerate().for_each(|(n, step)| {
+            let size = format_size(current.used[self.index(step.from)]);
+            current = self.apply(&current, step);
+            frames.push(format!(
+                "Move {}: {size} from x={},y={} to x={},y={}\n{}",
+                n + 1,
+                step.from.0,
+                step.from.1,
+                step.to.0,
+                step.to.1,
+                self.render(&current)
+            ));
+        });
+        frames.join("\n\n")
     }
 }
 
 fn main() {
     let input = read_input("day22.txt");
-    let cluster = Cluster::from(input.as_str());
-    println!("Part 1 = {}", cluster.viable_pairs().len());
-    println!("Part 2 = {}", cluster.move_data_to_goal());
+    let cluster = Cluster::try_from(input.as_str()).unwrap();
+    println!("Part 1 = {}", cluster.count_viable_pairs());
+    println!("Part 2 = {}", cluster.count_moves().unwrap());
+    match env::args().nth(1).as_deref() {
+        Some("--pairs") => cluster.viable_pairs().for_each(|(node, other)| {
+            println!(
+                "x={},y={} ({}) -> x={},y={} ({} free)",
+                node.x,
+                node.y,
+                format_size(node.used),
+                other.x,
+                other.y,
+                format_size(other.avail())
+            )
+        }),
+        Some("--replay") => println!("{}", cluster.replay(&cluster.move_data_to_goal().unwrap())),
+        _ => (),
+    }
 }
 
 #[cfg(test)]
 mod day22_tests {
+    use parameterized::parameterized;
+
     use super::*;
 
+    const TERABYTE: usize = 1 << 20;
+
     #[test]
     fn test_parse() {
         let input = "/dev/grid/node-x0-y0     91T   66T    25T   72%";
-        let node = Node::from(input);
+        let node = Node::try_from(input).unwrap();
         assert_eq!(
             node,
             Node {
                 x: 0,
                 y: 0,
-                size: 91,
-                used: 66
+                size: 91 * TERABYTE,
+                used: 66 * TERABYTE
             }
         );
-        assert_eq!(node.avail(), 25);
+        assert_eq!(node.avail(), 25 * TERABYTE);
+    }
+
+    #[test]
+    fn test_parse_df() {
+        let input = r#"me@home:~$ df -h | grep node
+some banner
+Filesystem            Size  Used Avail Use% Mounted on
+
+/dev/grid/node-x0-y0  1.5T  512G  1.0T  34% /mnt/x0
+/dev/grid/node-x1-y0    9T     0    9T   0% /mnt/x1
+/dev/grid/node-x0-y1    2G  1.5G  500M 75.5% /mnt/x2
+/dev/grid/node-x1-y1    1T    1T    0M 100%"#;
+        let cluster = Cluster::try_from(input).unwrap();
+        assert_eq!(cluster.nodes.width(), 2);
+        assert_eq!(cluster.nodes.height(), 2);
+        let sizes = cluster
+            .nodes
+            .iter()
+            .map(|(_, node)| (node.size, node.used))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            vec![
+                (1536 * 1024, 512 * 1024),
+                (9 * TERABYTE, 0),
+                (2048, 1536),
+                (TERABYTE, TERABYTE)
+            ]
+        );
+        assert_eq!(format_size(1536 * 1024), "1536G");
+        assert_eq!(format_size(TERABYTE), "1T");
+        assert_eq!(format_size(900), "900M");
+    }
+
+    #[test]
+    fn test_parse_json() {
+        let expected = cluster_of(&[(0, 0, 10, 8), (1, 0, 9, 0)]);
+        let listing = r#"[
+  {"filesystem": "/dev/grid/node-x1-y0", "size": "9T", "used": "0T", "avail": "9T", "use%": "0%"},
+  {"filesystem": "/dev/grid/node-x0-y0", "size": "10T", "used": "8T"}
+]"#;
+        assert_eq!(Cluster::try_from(listing), Ok(expected.clone()));
+        let findmnt = r#"{
+   "filesystems": [
+      {"source": "/dev/grid/node-x0-y0", "fstype": "ext4", "size": "10T", "used": "8T", "avail": "2T", "use%": "80%", "target": "/mnt/x0"},
+      {"source": "/dev/grid/node-x1-y0", "fstype": "ext4", "size": "9T", "used": "0T", "avail": "9T", "use%": "0%", "target": "/mnt/x1"}
+   ]
+}"#;
+        assert_eq!(Cluster::try_from(findmnt), Ok(expected));
+    }
+
+    #[parameterized(
+        input = {
+            "Filesystem Size Used Avail Use%\n/dev/grid/node-x0-y0 10T 8T 2T 80%\n/dev/grid/node-x1-y0 10X 8T 2T 80%",
+            "header\n/dev/grid/node-x0-y0 10T 8T 2T 80%\n/dev/sda1 10T 8T 2T 80%",
+            "header\n/dev/grid/node-x0-y0 10T 8T 2T 80%\ntotal",
+            "header\n/dev/grid/node-x0-y0 10T 8T 2T eighty",
+            "header\n/dev/grid/node-x0-y0 10T 12T 2T 80%",
+            "header\n/dev/grid/node-x0-y0 10T 8T 2T 80%\n/dev/grid/node-x0-y0 10T 8T 2T 80%",
+            "[\n{\"filesystem\": \"/dev/grid/node-x0-y0\", \"size\": \"10T\", \"used\": \"8T\"},\n{\"filesystem\": \"/dev/grid/node-x0-y0\", \"size\": \"10T\", \"used\": \"8T\"}\n]",
+            "[\n{\"filesystem\": \"/dev/grid/node-x0-y0\",\n\"size\": \"10\", \"used\": \"8T\"}\n]",
+            "header\n/dev/grid/node-x0-y0 10T 8T 2T 80%\n/dev/grid/node-x1-y1 10T 8T 2T 80%",
+            "Filesystem Size Used Avail Use%",
+        },
+        expected = {
+            "Line 3: `10X` is not a size in T, G or M",
+            "Line 3: `/dev/sda1` does not name a node as in node-x0-y0",
+            "Line 3: expected a file system, its size, used and available space",
+            "Line 2: `eighty` is not a percentage",
+            "Line 2: node x=0,y=0 uses more than its size",
+            "Line 3: node x=0,y=0 is listed twice",
+            "Line 3: node x=0,y=0 is listed twice",
+            "Line 2: `10` is not a size in T, G or M",
+            "Node x=1,y=0 is missing",
+            "No nodes listed",
+        }
+    )]
+    fn test_parse_errors(input: &str, expected: &str) {
+        assert_eq!(Cluster::try_from(input).unwrap_err().to_string(), expected);
     }
 
     #[test]
@@ -288,8 +789,8 @@ Filesystem            Size  Used  Avail  Use%
 /dev/grid/node-x2-y0   10T    6T     4T   60%
 /dev/grid/node-x2-y1    9T    8T     1T   88%
 /dev/grid/node-x2-y2    9T    6T     3T   66%"#;
-        let cluster = Cluster::from(input);
-        assert_eq!(cluster.move_data_to_goal(), 7);
+        let cluster = Cluster::try_from(input).unwrap();
+        assert_eq!(cluster.count_moves(), Some(7));
     }
 
     #[test]
@@ -305,9 +806,199 @@ Filesystem            Size  Used  Avail  Use%
 /dev/grid/node-x2-y0   10T    6T     4T   60%
 /dev/grid/node-x2-y1    9T    8T     1T   88%
 /dev/grid/node-x2-y2    9T    6T     3T   66%"#;
-        let cluster = Cluster::from(input);
-        let node_to = Node::from("/dev/grid/node-x2-y0   10T    6T     4T   60%");
-        let node_from = Node::from("/dev/grid/node-x1-y0    9T    0T     2T   77%");
-        assert!(cluster.viable_neighbors().contains(&(node_from, node_to)));
+        let cluster = Cluster::try_from(input).unwrap();
+        let state = cluster.initial_state();
+        let next_states = cluster.next_states(&state);
+        // The goal data moves to the empty node next to it
+        let mut used = state.used.clone();
+        used[1] = 6 * TERABYTE;
+        used[2] = 0;
+        assert!(next_states.contains(&ClusterState { goal: (1, 0), used }));
+        assert!(next_states
+            .iter()
+            .all(|next| next.used.iter().sum::<usize>() == 76 * TERABYTE));
+    }
+
+    #[test]
+    fn viable_pairs() {
+        // Sizes and usages all over the place, with a few empty nodes and
+        // nodes that could hold their own data
+        (0..5).for_each(|seed| {
+            let nodes = (0..12)
+                .flat_map(|y| (0..9).map(move |x| (x, y)))
+                .map(|(x, y)| {
+                    let size = 20 + (x * 13 + y * 7 + seed * 5) % 40;
+                    let used = match (x * 3 + y * 11 + seed) % 17 {
+                        0 => 0,
+                        n => (size * n / 17).min(size),
+                    };
+                    (x, y, size, used)
+                })
+                .collect::<Vec<_>>();
+            let cluster = cluster_of(&nodes);
+            let mut brute_force = vec![];
+            cluster.nodes.iter().for_each(|(_, node)| {
+                cluster.nodes.iter().for_each(|(_, other)| {
+                    if node.is_viable_pair(other) {
+                        brute_force.push((node, other));
+                    }
+                })
+            });
+            assert!(!brute_force.is_empty());
+            assert_eq!(cluster.count_viable_pairs(), brute_force.len());
+            let mut pairs = cluster.viable_pairs().collect::<Vec<_>>();
+            pairs.sort();
+            brute_force.sort();
+            assert_eq!(pairs, brute_force);
+        });
+    }
+
+    #[test]
+    fn test_replay() {
+        let input = r#"root@ebhq-gridcenter# df -h
+Filesystem            Size  Used  Avail  Use%
+/dev/grid/node-x0-y0   10T    8T     2T   80%
+/dev/grid/node-x0-y1   11T    6T     5T   54%
+/dev/grid/node-x0-y2   32T   28T     4T   87%
+/dev/grid/node-x1-y0    9T    7T     2T   77%
+/dev/grid/node-x1-y1    8T    0T     8T    0%
+/dev/grid/node-x1-y2   11T    7T     4T   63%
+/dev/grid/node-x2-y0   10T    6T     4T   60%
+/dev/grid/node-x2-y1    9T    8T     1T   88%
+/dev/grid/node-x2-y2    9T    6T     3T   66%"#;
+        let cluster = Cluster::try_from(input).unwrap();
+        assert_eq!(
+            cluster.render(&cluster.initial_state()),
+            r#"(.) .  G
+ .  _  .
+ #  .  ."#
+        );
+        let moves = cluster.move_data_to_goal().unwrap();
+        assert_eq!(
+            moves[0],
+            Move {
+                from: (1, 0),
+                to: (1, 1)
+            }
+        );
+        let listing = cluster.replay(&moves);
+        assert!(listing.starts_with(&cluster.render(&cluster.initial_state())));
+        assert!(
+            listing.contains("Move 1: 7T from x=1,y=0 to x=1,y=1\n(.) _  G\n .  .  .\n #  .  .")
+        );
+        assert!(
+            listing.contains("Move 2: 6T from x=2,y=0 to x=1,y=0\n(.) G  _\n .  .  .\n #  .  .")
+        );
+        assert!(
+            listing.ends_with("Move 7: 6T from x=1,y=0 to x=0,y=0\n(G) _  .\n .  .  .\n #  .  .")
+        );
+
+        // The full search replays to the same cluster
+        let moves = cluster.search_all_moves().unwrap();
+        let last = moves.iter().fold(cluster.initial_state(), |state, step| {
+            assert!(cluster
+                .next_states(&state)
+                .contains(&cluster.apply(&state, step)));
+            cluster.apply(&state, step)
+        });
+        assert_eq!(last.goal, (0, 0));
+        assert!(cluster
+            .replay(&moves)
+            .ends_with("(G) _  .\n .  .  .\n #  .  ."));
+    }
+
+    fn cluster_of(nodes: &[(usize, usize, usize, usize)]) -> Cluster {
+        let lines = nodes
+            .iter()
+            .map(|(x, y, size, used)| {
+                format!(
+                    "/dev/grid/node-x{x}-y{y} {size}T {used}T {}T 0%",
+                    size - used
+                )
+            })
+            .collect::<Vec<_>>();
+        Cluster::try_from(
+            format!(
+                "df -h\nFilesystem Size Used Avail Use%\n{}",
+                lines.join("\n")
+            )
+            .as_str(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn searches_agree() {
+        let input = r#"root@ebhq-gridcenter# df -h
+Filesystem            Size  Used  Avail  Use%
+/dev/grid/node-x0-y0   10T    8T     2T   80%
+/dev/grid/node-x0-y1   11T    6T     5T   54%
+/dev/grid/node-x0-y2   32T   28T     4T   87%
+/dev/grid/node-x1-y0    9T    7T     2T   77%
+/dev/grid/node-x1-y1    8T    0T     8T    0%
+/dev/grid/node-x1-y2   11T    7T     4T   63%
+/dev/grid/node-x2-y0   10T    6T     4T   60%
+/dev/grid/node-x2-y1    9T    8T     1T   88%
+/dev/grid/node-x2-y2    9T    6T     3T   66%"#;
+        let cluster = Cluster::try_from(input).unwrap();
+        assert!(cluster.has_interchangeable_data());
+        assert_eq!(
+            cluster.move_holes_to_goal().map(|moves| moves.len()),
+            Some(7)
+        );
+        assert_eq!(cluster.search_all_moves().map(|moves| moves.len()), Some(7));
+
+        // Two empty nodes let the goal data slide without going around
+        let cluster = cluster_of(&[
+            (0, 0, 10, 0),
+            (1, 0, 10, 0),
+            (2, 0, 10, 7),
+            (0, 1, 10, 6),
+            (1, 1, 10, 7),
+            (2, 1, 10, 8),
+        ]);
+        assert!(cluster.has_interchangeable_data());
+        assert_eq!(
+            cluster.move_holes_to_goal().map(|moves| moves.len()),
+            Some(2)
+        );
+        assert_eq!(cluster.search_all_moves().map(|moves| moves.len()), Some(2));
+    }
+
+    #[test]
+    fn partial_transfers() {
+        // The goal data fits next to the data already there
+        let cluster = cluster_of(&[(0, 0, 10, 3), (1, 0, 10, 4)]);
+        assert!(!cluster.has_interchangeable_data());
+        assert_eq!(cluster.count_moves(), Some(1));
+        // Room has to be made first
+        let cluster = cluster_of(&[(0, 0, 6, 3), (1, 0, 10, 4), (0, 1, 10, 2), (1, 1, 10, 9)]);
+        assert_eq!(cluster.count_moves(), Some(2));
+        // Data merged into the goal node moves along with it
+        let cluster = cluster_of(&[(0, 0, 5, 3), (1, 0, 10, 4)]);
+        assert_eq!(cluster.count_moves(), None);
+        // Nothing fits anywhere
+        let cluster = cluster_of(&[(0, 0, 5, 3), (1, 0, 5, 4)]);
+        assert_eq!(cluster.count_moves(), None);
+    }
+
+    #[test]
+    fn large_cluster() {
+        // 100x100 nodes, a wall across most of row 10 and an empty node in
+        // the middle
+        let nodes = (0..100)
+            .flat_map(|y| (0..100).map(move |x| (x, y)))
+            .map(|(x, y)| match (x, y) {
+                (50, 50) => (x, y, 90, 0),
+                (20.., 10) => (x, y, 500, 490),
+                _ => (x, y, 90, 70 + (x * 7 + y * 3) % 10),
+            })
+            .collect::<Vec<_>>();
+        let cluster = cluster_of(&nodes);
+        assert!(cluster.has_interchangeable_data());
+        // Around the wall, up to the node left of the goal data, then five
+        // moves for every further step
+        let approach = (50 - 19) + 50 + (98 - 19);
+        assert_eq!(cluster.count_moves(), Some(approach + 1 + 5 * 98));
     }
 }
@@ -1,10 +1,11 @@
-use std::{
-    cmp::min,
-    collections::{HashMap, VecDeque},
-    ops::Deref,
-};
+use std::{cmp::Ordering, error::Error, fmt::Display};
 
-use common::read_input;
+use common::{
+    grid::Grid,
+    read_input,
+    search::bfs_distances,
+    tsp::{Route, Solver, Tour},
+};
 
 #[derive(Debug, PartialEq)]
 enum Tile {
@@ -18,7 +19,7 @@ impl From<char> for Tile {
         match value {
             '.' => Self::Floor,
             '#' => Self::Wall,
-            c if c.is_digit(10) => Self::Checkpoint(c.to_digit(10).unwrap() as usize),
+            c if c.is_ascii_digit() => Self::Checkpoint(c.to_digit(10).unwrap() as usize),
             c => panic!("Unknown tile {c}"),
         }
     }
@@ -26,178 +27,130 @@ impl From<char> for Tile {
 
 impl Tile {
     fn is_walkable(&self) -> bool {
+        !matches!(self, Tile::Wall)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum MazeError {
+    MissingCheckpoint(usize),
+    DuplicateCheckpoint(usize),
+    Unreachable { from: usize, to: usize },
+}
+
+impl Display for MazeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Tile::Wall => false,
-            _ => true,
+            MazeError::MissingCheckpoint(cp) => write!(f, "Checkpoint {cp} is missing"),
+            MazeError::DuplicateCheckpoint(cp) => {
+                write!(f, "Checkpoint {cp} appears more than once")
+            }
+            MazeError::Unreachable { from, to } => {
+                write!(
+                    f,
+                    "Checkpoint {to} cannot be reached from checkpoint {from}"
+                )
+            }
         }
     }
 }
 
+impl Error for MazeError {}
+
 #[derive(Debug)]
 struct Maze {
-    tiles: Vec<Vec<Tile>>,
-    rows: usize,
-    cols: usize,
-    checkpoints: Vec<usize>,
-    cost: HashMap<(usize, usize), usize>,
+    tiles: Grid<Tile>,
+    // Position of every checkpoint, indexed by its number
+    checkpoints: Vec<(usize, usize)>,
+    // Fewest steps between every two checkpoints, indexed by their numbers
+    cost: Vec<Vec<usize>>,
 }
 
-impl Deref for Maze {
-    type Target = Vec<Vec<Tile>>;
-
-    fn deref(&self) -> &Self::Target {
-        &self.tiles
-    }
-}
+impl TryFrom<&str> for Maze {
+    type Error = MazeError;
 
-impl From<&str> for Maze {
-    fn from(value: &str) -> Self {
-        let tiles = value
-            .trim()
-            .lines()
-            .map(|l| l.trim().chars().map(|c| c.into()).collect::<Vec<_>>())
-            .collect::<Vec<_>>();
-        let rows = tiles.len();
-        let cols = tiles.iter().nth(0).unwrap().len();
-        let checkpoints = tiles
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let value = value.trim().lines().map(str::trim).collect::<Vec<_>>();
+        let tiles = Grid::<Tile>::from(value.join("\n").as_str());
+        let mut checkpoints = tiles
             .iter()
-            .flat_map(|row| {
-                row.iter()
-                    .filter_map(|t| match t {
-                        Tile::Checkpoint(p) => Some(*p),
-                        _ => None,
-                    })
-                    .collect::<Vec<_>>()
+            .filter_map(|(position, t)| match t {
+                Tile::Checkpoint(p) => Some((*p, position)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        checkpoints.sort();
+        let checkpoints = checkpoints
+            .into_iter()
+            .enumerate()
+            // Sorted, checkpoint `n` is at index `n` unless one is missing or
+            // repeated before it
+            .map(|(n, (cp, position))| match cp.cmp(&n) {
+                Ordering::Equal => Ok(position),
+                Ordering::Less => Err(MazeError::DuplicateCheckpoint(cp)),
+                Ordering::Greater => Err(MazeError::MissingCheckpoint(n)),
             })
-            .collect();
+            .collect::<Result<Vec<_>, _>>()?;
         let mut maze = Self {
             tiles,
-            rows,
-            cols,
             checkpoints,
-            cost: HashMap::new(),
+            cost: vec![],
         };
-        maze.prepare_distance_matrix();
-        maze
+        maze.prepare_distance_matrix()?;
+        Ok(maze)
     }
 }
 
 impl Maze {
-    fn cp_position(&self, cp: usize) -> (usize, usize) {
-        *(0..self.rows)
-            .flat_map(|row| (0..self.cols).map(|col| (col, row)).collect::<Vec<_>>())
-            .collect::<Vec<_>>()
-            .iter()
-            .find(|(col, row)| self[*row][*col] == Tile::Checkpoint(cp))
-            .unwrap()
-    }
-
-    fn neighbors(&self, (position_x, position_y): (usize, usize)) -> Vec<(usize, usize)> {
-        let mut deltas: Vec<(isize, isize)> = vec![];
-        if self[position_y][position_x - 1].is_walkable() {
-            deltas.push((-1, 0));
-        };
-        if self[position_y][position_x + 1].is_walkable() {
-            deltas.push((1, 0));
-        };
-        if self[position_y - 1][position_x].is_walkable() {
-            deltas.push((0, -1));
-        };
-        if self[position_y + 1][position_x].is_walkable() {
-            deltas.push((0, 1));
-        };
-        deltas
-            .iter()
-            .map(|(delta_x, delta_y)| {
-                (
-                    (position_x as isize + delta_x) as usize,
-                    (position_y as isize + delta_y) as usize,
-                )
-            })
+    fn neighbors(&self, position: (usize, usize)) -> Vec<(usize, usize)> {
+        self.tiles
+            .neighbors4(position)
+            .filter(|p| self.tiles[*p].is_walkable())
             .collect()
     }
 
-    fn min_steps_from_to(&self, cp_start: usize, cp_end: usize) -> usize {
-        let start = self.cp_position(cp_start);
-        let end = self.cp_position(cp_end);
-        let mut queue = VecDeque::new();
-        queue.push_back((start, 0));
-        let mut visited = vec![start];
-        while let Some((current, depth)) = queue.pop_front() {
-            if current == end {
-                return depth;
-            }
-            self.neighbors(current).iter().for_each(|neighbor| {
-                if !visited.contains(neighbor) {
-                    visited.push(*neighbor);
-                    queue.push_back((*neighbor, depth + 1));
-                }
-            });
-        }
-        unreachable!("Target cannot be reached");
-    }
-
-    fn prepare_distance_matrix(&mut self) {
-        let mut distance_matrix: HashMap<(usize, usize), usize> = HashMap::new();
-        self.checkpoints
+    /// Floods the maze once from every checkpoint, filling a whole row of
+    /// the distance matrix each time.
+    fn prepare_distance_matrix(&mut self) -> Result<(), MazeError> {
+        self.cost = self
+            .checkpoints
             .iter()
             .enumerate()
-            .for_each(|(idx_from, cp_start)| {
-                self.checkpoints[idx_from..].iter().for_each(|cp_end| {
-                    let distance = self.min_steps_from_to(*cp_start, *cp_end);
-                    distance_matrix.insert((*cp_start, *cp_end), distance);
-                    distance_matrix.insert((*cp_end, *cp_start), distance);
-                })
-            });
-        self.cost = distance_matrix;
-    }
-
-    fn total_cost(
-        &self,
-        mask: usize,
-        current: usize,
-        n: usize,
-        memo: &mut Vec<Vec<Option<usize>>>,
-        and_return: bool,
-    ) -> usize {
-        if mask == (1 << n) - 1 {
-            if and_return {
-                return *self.cost.get(&(current, 0)).unwrap();
-            } else {
-                return 0;
-            }
-        }
-
-        if let Some(result) = memo[current][mask] {
-            return result;
-        }
-
-        let mut result = usize::max_value();
-        (0..n).for_each(|i| {
-            if mask & (1 << i) == 0 {
-                result = min(
-                    result,
-                    self.cost.get(&(current, i)).unwrap()
-                        + self.total_cost(mask | (1 << i), i, n, memo, and_return),
-                );
-            }
-        });
-        memo[current][mask] = Some(result);
-        result
+            .map(|(from, start)| {
+                let distances =
+                    bfs_distances(*start, usize::MAX, |&current| self.neighbors(current));
+                self.checkpoints
+                    .iter()
+                    .enumerate()
+                    .map(|(to, end)| {
+                        distances
+                            .get(end)
+                            .copied()
+                            .ok_or(MazeError::Unreachable { from, to })
+                    })
+                    .collect()
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(())
     }
 
-    fn tsp(&self, and_return: bool) -> usize {
-        let n = self.checkpoints.iter().max().unwrap() + 1;
-        let mut memo: Vec<Vec<Option<usize>>> = vec![vec![None; 1 << n]; n];
-        self.total_cost(1, 0, n, &mut memo, and_return)
+    /// Shortest visit of every checkpoint starting from 0, coming back to it
+    /// if `and_return` is set.
+    fn tsp(&self, and_return: bool) -> Tour {
+        let route = if and_return {
+            Route::Closed
+        } else {
+            Route::Open
+        };
+        Solver::new(route).start(0).solve(&self.cost)
     }
 }
 
 fn main() {
     let input = read_input("day24.txt");
-    let maze = Maze::from(input.as_str());
-    println!("Part 1 = {}", maze.tsp(false));
-    println!("Part 2 = {}", maze.tsp(true));
+    let maze = Maze::try_from(input.as_str()).unwrap();
+    println!("Part 1 = {}", maze.tsp(false).cost);
+    println!("Part 2 = {}", maze.tsp(true).cost);
 }
 
 #[cfg(test)]
@@ -211,14 +164,14 @@ mod day24_tests {
         to = { 4, 1, 2, 3 },
         expected = { 2, 4, 6, 2 }
     )]
-    fn test_min_steps_from_to(from: usize, to: usize, expected: usize) {
+    fn test_distance_matrix(from: usize, to: usize, expected: usize) {
         let input = r#"###########
 #0.1.....2#
 #.#######.#
 #4.......3#
 ###########"#;
-        let maze = Maze::from(input);
-        assert_eq!(maze.min_steps_from_to(from, to), expected);
+        let maze = Maze::try_from(input).unwrap();
+        assert_eq!(maze.cost[from][to], expected);
     }
 
     #[test]
@@ -228,7 +181,44 @@ mod day24_tests {
 #.#######.#
 #4.......3#
 ###########"#;
-        let maze = Maze::from(input);
-        assert_eq!(maze.tsp(false), 14);
+        let maze = Maze::try_from(input).unwrap();
+        let tour = maze.tsp(false);
+        assert_eq!(tour.cost, 14);
+        assert_eq!(tour.order, vec![0, 4, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_open_border() {
+        let input = r#"0.1
+...
+2.."#;
+        let maze = Maze::try_from(input).unwrap();
+        assert_eq!(maze.cost[0][2], 2);
+        assert_eq!(maze.tsp(true).cost, 8);
+    }
+
+    #[test]
+    fn test_errors() {
+        let input = r#"#####
+#0#1#
+#####"#;
+        assert_eq!(
+            Maze::try_from(input).unwrap_err(),
+            MazeError::Unreachable { from: 0, to: 1 }
+        );
+        let input = r#"#####
+#0.2#
+#####"#;
+        assert_eq!(
+            Maze::try_from(input).unwrap_err(),
+            MazeError::MissingCheckpoint(1)
+        );
+        let input = r#"#######
+#0.1.1#
+#######"#;
+        assert_eq!(
+            Maze::try_from(input).unwrap_err(),
+            MazeError::DuplicateCheckpoint(1)
+        );
     }
 }
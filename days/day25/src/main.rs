@@ -1,269 +1,150 @@
-use std::{collections::HashMap, fmt::Write};
+use std::{
+    ops::Range,
+    sync::atomic::{AtomicI32, AtomicU64, Ordering},
+    thread,
+};
 
-use common::read_input;
+use common::{assembunny::Computer, read_input};
 
-#[derive(Debug, Clone)]
-enum RegisterOrValue {
-    Register(char),
-    Value(i32),
+/// Runs the program once per candidate on all available cores and returns the
+/// lowest candidate whose output satisfies `predicate`.
+///
+/// Candidates are handed out in increasing order, so once a valid one is found
+/// every worker stops as soon as its next candidate is above it.
+fn find_lowest_candidate<S, P>(
+    computer: &Computer,
+    candidates: Range<i32>,
+    setup: S,
+    predicate: P,
+) -> Option<i32>
+where
+    S: Fn(&mut Computer, i32) + Sync,
+    P: Fn(&[i32]) -> bool + Sync,
+{
+    // Counted from the start of the range, so that handing out candidates
+    // past the end never wraps around to lower ones
+    let next = AtomicU64::new(0);
+    let lowest = AtomicI32::new(candidates.end);
+    let workers = thread::available_parallelism().map_or(1, |n| n.get());
+    thread::scope(|s| {
+        (0..workers).for_each(|_| {
+            s.spawn(|| loop {
+                let offset = next.fetch_add(1, Ordering::SeqCst);
+                let candidate = i64::from(candidates.start) + offset as i64;
+                if candidate >= i64::from(lowest.load(Ordering::SeqCst)) {
+                    break;
+                }
+                // Below the end of the range, so it fits
+                let candidate = candidate as i32;
+                let mut computer = computer.clone();
+                setup(&mut computer, candidate);
+                let mut output = vec![];
+                computer
+                    .run_with(
+                        |value| {
+                            output.push(value);
+                            Ok(())
+                        },
+                        1_000_000,
+                    )
+                    .unwrap();
+                if predicate(&output) {
+                    lowest.fetch_min(candidate, Ordering::SeqCst);
+                }
+            });
+        });
+    });
+    let lowest = lowest.into_inner();
+    (lowest < candidates.end).then_some(lowest)
 }
 
-impl From<&str> for RegisterOrValue {
-    fn from(value: &str) -> Self {
-        if let Ok(n) = value.parse::<i32>() {
-            Self::Value(n)
-        } else if let Some(c) = value.chars().nth(0) {
-            Self::Register(c)
-        } else {
-            panic!("Unknown entity {value}")
-        }
-    }
+fn is_clock_signal(output: &[i32]) -> bool {
+    output.len() >= 2
+        && output
+            .iter()
+            .enumerate()
+            .all(|(i, value)| *value == (i % 2) as i32)
 }
 
-fn str_to_char(s: &str) -> char {
-    if s.len() != 1 {
-        panic!("String {s} is not a char");
-    }
-    s.chars().nth(0).unwrap()
+fn main() {
+    let input = read_input("day25.txt");
+    let computer = Computer::try_from(input.as_str()).unwrap();
+    let part1 = find_lowest_candidate(
+        &computer,
+        0..i32::MAX,
+        |computer, i| computer.set_register('a', i),
+        is_clock_signal,
+    );
+    println!("Part 1 = {}", part1.unwrap());
 }
 
-#[derive(Debug, Clone)]
-enum Instruction {
-    Cpy(RegisterOrValue, RegisterOrValue),
-    Inc(char),
-    Dec(char),
-    Jnz(RegisterOrValue, RegisterOrValue),
-    Tgl(char),
-    Out(RegisterOrValue),
-}
+#[cfg(test)]
+mod day25_tests {
+    use super::*;
 
-impl From<&str> for Instruction {
-    fn from(value: &str) -> Self {
-        let mut split = value.split_whitespace();
-        match split.next().unwrap() {
-            "cpy" => Self::Cpy(split.next().unwrap().into(), split.next().unwrap().into()),
-            "inc" => Self::Inc(str_to_char(split.next().unwrap())),
-            "dec" => Self::Dec(str_to_char(split.next().unwrap())),
-            "jnz" => Self::Jnz(split.next().unwrap().into(), split.next().unwrap().into()),
-            "tgl" => Self::Tgl(str_to_char(split.next().unwrap())),
-            "out" => Self::Out(split.next().unwrap().into()),
-            s => panic!("Unknown instruction {s}"),
-        }
+    fn counter() -> Computer {
+        let input = r#"out a
+inc a
+dec b
+jnz b -3"#;
+        Computer::try_from(input).unwrap()
     }
-}
-
-struct Computer {
-    registers: HashMap<char, i32>,
-    instructions: Vec<Instruction>,
-    cursor: i32,
-}
 
-impl From<&str> for Computer {
-    fn from(value: &str) -> Self {
-        let instructions = value
-            .trim()
-            .lines()
-            .map(|l| l.trim().into())
-            .collect::<Vec<Instruction>>();
-        let mut registers = HashMap::new();
-        registers.insert('a', 0);
-        registers.insert('b', 0);
-        registers.insert('c', 0);
-        registers.insert('d', 0);
-        Self {
-            registers,
-            instructions,
-            cursor: 0,
-        }
+    #[test]
+    fn test_find_lowest_candidate() {
+        let computer = counter();
+        let setup = |computer: &mut Computer, i| {
+            computer.set_register('a', i);
+            computer.set_register('b', 3);
+        };
+        assert_eq!(
+            find_lowest_candidate(&computer, 0..100, setup, |output| output == [7, 8, 9]),
+            Some(7)
+        );
+        assert_eq!(
+            find_lowest_candidate(&computer, 0..100, setup, |output| output.ends_with(&[9])),
+            Some(7)
+        );
+        assert_eq!(
+            find_lowest_candidate(&computer, 0..5, setup, |output| output == [7, 8, 9]),
+            None
+        );
+        // Values of several digits are told apart from runs of single ones
+        assert_eq!(
+            find_lowest_candidate(&computer, 0..100, setup, |output| output == [10, 11, 12]),
+            Some(10)
+        );
+        assert_eq!(
+            find_lowest_candidate(&computer, 0..100, setup, |output| output == [1, 0, 1]),
+            None
+        );
     }
-}
 
-fn process_multiply(instructions: &[Instruction]) -> Option<(char, char, char, char)> {
-    let cpy =
-        if let Instruction::Cpy(RegisterOrValue::Register(r1), RegisterOrValue::Register(r2)) =
-            instructions[0]
-        {
-            (r1, r2)
-        } else {
-            return None;
+    #[test]
+    fn test_find_lowest_candidate_up_to_max() {
+        let computer = counter();
+        let setup = |computer: &mut Computer, i| {
+            computer.set_register('a', i);
+            computer.set_register('b', 1);
         };
-    let inc = if let Instruction::Inc(r) = instructions[1] {
-        r
-    } else {
-        return None;
-    };
-    let dec1 = if let Instruction::Dec(r) = instructions[2] {
-        r
-    } else {
-        return None;
-    };
-    let jnz1 = if let Instruction::Jnz(RegisterOrValue::Register(r), _) = instructions[3] {
-        r
-    } else {
-        return None;
-    };
-    let dec2 = if let Instruction::Dec(r) = instructions[4] {
-        r
-    } else {
-        return None;
-    };
-    let jnz2 = if let Instruction::Jnz(RegisterOrValue::Register(r), _) = instructions[5] {
-        r
-    } else {
-        return None;
-    };
-    if !(cpy.1 == dec1 && cpy.1 == jnz1 && dec2 == jnz2) {
-        return None;
-    };
-    Some((cpy.0, dec2, cpy.1, inc))
-}
-
-impl Computer {
-    fn set_register(&mut self, r: char, v: i32) {
-        self.registers.entry(r).and_modify(|value| *value = v);
+        let end = i32::MAX;
+        assert_eq!(
+            find_lowest_candidate(&computer, end - 20..end, setup, |output| output
+                == [end - 1]),
+            Some(end - 1)
+        );
+        assert_eq!(
+            find_lowest_candidate(&computer, end - 20..end, setup, |output| output.is_empty()),
+            None
+        );
     }
 
-    fn execute<W: Write>(&mut self, mut output: W) {
-        let mut count = 0;
-        while let Some(instruction) = self.instructions.get(self.cursor as usize) {
-            if count == 1000000 {
-                return;
-            }
-            let process_multiply = if self.cursor as usize + 6 < self.instructions.len() {
-                process_multiply(&self.instructions[self.cursor as usize..self.cursor as usize + 6])
-            } else {
-                None
-            };
-            if let Some((r1, r2, r3, rd)) = process_multiply {
-                let mult1 = self.registers.get(&r1).unwrap().clone();
-                let mult2 = self.registers.get(&r2).unwrap().clone();
-                self.registers
-                    .entry(rd)
-                    .and_modify(|value| *value += mult1 * mult2);
-                self.registers.entry(r2).and_modify(|value| *value = 0);
-                self.registers.entry(r3).and_modify(|value| *value = 0);
-                self.cursor += 6;
-            } else {
-                match instruction {
-                    Instruction::Cpy(v, r) => {
-                        if let RegisterOrValue::Register(r) = &r {
-                            match v {
-                                RegisterOrValue::Register(reg) => {
-                                    let reg_value = self.registers.get(reg).unwrap().clone();
-                                    self.registers
-                                        .entry(*r)
-                                        .and_modify(|value| *value = reg_value)
-                                        .or_insert(reg_value)
-                                }
-                                RegisterOrValue::Value(val) => self
-                                    .registers
-                                    .entry(*r)
-                                    .and_modify(|value| *value = *val)
-                                    .or_insert(*val),
-                            };
-                        }
-                        self.cursor += 1;
-                    }
-                    Instruction::Inc(r) => {
-                        self.registers
-                            .entry(*r)
-                            .and_modify(|value| *value += 1)
-                            .or_insert(1);
-                        self.cursor += 1;
-                    }
-                    Instruction::Dec(r) => {
-                        self.registers
-                            .entry(*r)
-                            .and_modify(|value| *value -= 1)
-                            .or_insert(-1);
-                        self.cursor += 1;
-                    }
-                    Instruction::Jnz(v, c) => match c {
-                        RegisterOrValue::Value(c) => {
-                            let must_jump = match v {
-                                RegisterOrValue::Register(reg) => {
-                                    *self.registers.get(reg).unwrap() != 0
-                                }
-                                RegisterOrValue::Value(val) => *val != 0,
-                            };
-                            if must_jump {
-                                self.cursor += c;
-                            } else {
-                                self.cursor += 1;
-                            }
-                        }
-                        RegisterOrValue::Register(r) => {
-                            let must_jump = match v {
-                                RegisterOrValue::Register(reg) => {
-                                    *self.registers.get(reg).unwrap() != 0
-                                }
-                                RegisterOrValue::Value(val) => *val != 0,
-                            };
-                            if must_jump {
-                                self.cursor += self.registers.get(r).unwrap();
-                            } else {
-                                self.cursor += 1;
-                            }
-                        }
-                    },
-                    Instruction::Tgl(r) => {
-                        if let Some(pos) = self.registers.get(r) {
-                            let pos = pos + self.cursor;
-                            if pos < self.instructions.len() as i32 {
-                                let new_instruction = if let Some(instruction) =
-                                    self.instructions.get(pos as usize)
-                                {
-                                    match instruction {
-                                        Instruction::Inc(r) => Some(Instruction::Dec(*r)),
-                                        Instruction::Dec(r) => Some(Instruction::Inc(*r)),
-                                        Instruction::Cpy(rov, c) => {
-                                            Some(Instruction::Jnz(rov.clone(), c.clone()))
-                                        }
-                                        Instruction::Jnz(rov, c) => {
-                                            Some(Instruction::Cpy(rov.clone(), c.clone()))
-                                        }
-                                        Instruction::Tgl(r) => Some(Instruction::Inc(*r)),
-                                        Instruction::Out(r) => Some(Instruction::Out(r.clone())),
-                                    }
-                                } else {
-                                    None
-                                };
-                                if let Some(instruction) = new_instruction {
-                                    self.instructions.remove(pos as usize);
-                                    self.instructions.insert(pos as usize, instruction);
-                                }
-                            }
-                        }
-                        self.cursor += 1;
-                    }
-                    Instruction::Out(r) => {
-                        match r {
-                            RegisterOrValue::Register(reg) => {
-                                let reg_value = self.registers.get(reg).unwrap().clone();
-                                write!(&mut output, "{}", reg_value).unwrap();
-                            }
-                            RegisterOrValue::Value(val) => {
-                                write!(&mut output, "{}", val).unwrap();
-                            }
-                        };
-                        self.cursor += 1;
-                    }
-                }
-            };
-            count += 1;
-        }
+    #[test]
+    fn test_is_clock_signal() {
+        assert!(is_clock_signal(&[0, 1, 0, 1, 0, 1]));
+        assert!(!is_clock_signal(&[1, 0, 1, 0, 1, 0]));
+        assert!(!is_clock_signal(&[0, 1, 0, 1, 1, 0]));
+        assert!(!is_clock_signal(&[0, 1, 0, 10]));
+        assert!(!is_clock_signal(&[]));
     }
 }
-
-fn main() {
-    let input = read_input("day25.txt");
-    (0..200).for_each(|i| {
-        let mut computer = Computer::from(input.as_str());
-        computer.set_register('a', i);
-        let mut output = String::new();
-        computer.execute(&mut output);
-        if output.starts_with("01010101010101") || output.starts_with("10101010101010") {
-            println!("{i} -> {}", output);
-        }
-    });
-}
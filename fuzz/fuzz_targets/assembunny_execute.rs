@@ -0,0 +1,18 @@
+#![no_main]
+
+use common::assembunny::Computer;
+use libfuzzer_sys::fuzz_target;
+
+const MAX_STEPS: usize = 10_000;
+
+fuzz_target!(|input: ([i32; 4], &str)| {
+    let (registers, source) = input;
+    if let Ok(mut computer) = Computer::try_from(source) {
+        ['a', 'b', 'c', 'd']
+            .into_iter()
+            .zip(registers)
+            .for_each(|(r, v)| computer.set_register(r, v));
+        let mut output = String::new();
+        computer.run(&mut output, MAX_STEPS).unwrap();
+    }
+});
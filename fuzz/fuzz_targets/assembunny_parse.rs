@@ -0,0 +1,11 @@
+#![no_main]
+
+use common::assembunny::{assemble, Instruction};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    source.lines().for_each(|l| {
+        let _ = Instruction::try_from(l);
+    });
+    let _ = assemble(source);
+});